@@ -315,6 +315,18 @@ mod gen {
                     },
                     timeout_ms: i64,
                 },
+                xhr: {
+                    global_privacy_control: {
+                        enabled: bool,
+                    },
+                    max_request_header_bytes: i64,
+                    max_request_headers: i64,
+                    max_response_text_bytes: i64,
+                    testing: {
+                        #[serde(default)]
+                        enabled: bool,
+                    },
+                },
             },
             gfx: {
                 subpixel_text_antialiasing: {
@@ -3,14 +3,17 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::hosts::replace_host;
-use hyper::client::connect::{Connect, Destination};
+use hyper::client::connect::{Connect, Connected, Destination};
 use hyper::client::HttpConnector as HyperHttpConnector;
 use hyper::rt::Future;
 use hyper::{Body, Client};
-use hyper_openssl::HttpsConnector;
+use hyper_openssl::{HttpsConnector, MaybeHttpsStream};
+use net_traits::TlsInfo;
 use openssl::ssl::{SslConnector, SslConnectorBuilder, SslMethod, SslOptions};
 use openssl::x509;
-use tokio::prelude::future::Executor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::prelude::future::{Executor, Map};
 
 pub const BUF_SIZE: usize = 32768;
 pub const ALPN_H2_H1: &'static [u8] = b"\x02h2\x08http/1.1";
@@ -57,7 +60,74 @@ impl Connect for HttpConnector {
     }
 }
 
-pub type Connector = HttpsConnector<HttpConnector>;
+/// Attached to every response received over the same connection.
+#[derive(Clone)]
+pub struct ConnectionInfo {
+    uses: Arc<AtomicUsize>,
+    /// The names of the negotiated protocol and cipher, for https
+    /// connections. OpenSSL hands these out as static strings, so nothing is
+    /// copied unless a request asks for them.
+    tls: Option<(&'static str, Option<&'static str>)>,
+}
+
+impl ConnectionInfo {
+    /// Counts one more response over this connection, and returns whether
+    /// there were any before it.
+    pub fn record_use(&self) -> bool {
+        self.uses.fetch_add(1, Ordering::SeqCst) > 0
+    }
+
+    /// Details of the TLS session, for https connections.
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        self.tls.map(|(protocol, cipher)| TlsInfo {
+            protocol: protocol.to_owned(),
+            cipher: cipher.map(str::to_owned),
+        })
+    }
+}
+
+type Connection = (
+    <HttpsConnector<HttpConnector> as Connect>::Transport,
+    Connected,
+);
+
+fn attach_info((transport, connected): Connection) -> Connection {
+    let tls = match transport {
+        MaybeHttpsStream::Http(_) => None,
+        MaybeHttpsStream::Https(ref stream) => {
+            let ssl = stream.get_ref().ssl();
+            Some((
+                ssl.version_str(),
+                ssl.current_cipher().map(|cipher| cipher.name()),
+            ))
+        },
+    };
+    let info = ConnectionInfo {
+        uses: Arc::new(AtomicUsize::new(0)),
+        tls,
+    };
+    (transport, connected.extra(info))
+}
+
+/// The connector of the HTTP client, attaching a `ConnectionInfo` to the
+/// responses received over each connection.
+pub struct Connector {
+    inner: HttpsConnector<HttpConnector>,
+}
+
+impl Connect for Connector {
+    type Transport = <HttpsConnector<HttpConnector> as Connect>::Transport;
+    type Error = <HttpsConnector<HttpConnector> as Connect>::Error;
+    type Future =
+        Map<<HttpsConnector<HttpConnector> as Connect>::Future, fn(Connection) -> Connection>;
+
+    fn connect(&self, dest: Destination) -> Self::Future {
+        self.inner
+            .connect(dest)
+            .map(attach_info as fn(Connection) -> Connection)
+    }
+}
+
 pub type TlsConfig = SslConnectorBuilder;
 
 pub fn create_tls_config(certs: &str, alpn: &[u8]) -> TlsConfig {
@@ -111,7 +181,9 @@ pub fn create_http_client<E>(tls_config: TlsConfig, executor: E) -> Client<Conne
 where
     E: Executor<Box<dyn Future<Error = (), Item = ()> + Send + 'static>> + Sync + Send + 'static,
 {
-    let connector = HttpsConnector::with_connector(HttpConnector::new(), tls_config).unwrap();
+    let connector = Connector {
+        inner: HttpsConnector::with_connector(HttpConnector::new(), tls_config).unwrap(),
+    };
 
     Client::builder()
         .http1_title_case_headers(true)
@@ -25,7 +25,7 @@ use net_traits::response::{Response, ResponseBody, ResponseType};
 use net_traits::{FetchTaskTarget, NetworkError, ReferrerPolicy, ResourceFetchTiming};
 use net_traits::{ResourceAttribute, ResourceTimeValue};
 use servo_arc::Arc as ServoArc;
-use servo_url::ServoUrl;
+use servo_url::{ImmutableOrigin, ServoUrl};
 use std::borrow::Cow;
 use std::fs::File;
 use std::io::{BufReader, Seek, SeekFrom};
@@ -47,6 +47,7 @@ pub enum Data {
     Payload(Vec<u8>),
     Done,
     Cancelled,
+    Error(NetworkError),
 }
 
 pub struct FetchContext {
@@ -200,9 +201,7 @@ pub fn main_fetch(
 
     // Step 2.4.
     if should_request_be_blocked_by_csp(request) == csp::CheckResult::Blocked {
-        response = Some(Response::network_error(NetworkError::Internal(
-            "Blocked by Content-Security-Policy".into(),
-        )))
+        response = Some(Response::network_error(NetworkError::ContentSecurityPolicy))
     }
 
     // Step 3.
@@ -276,8 +275,19 @@ pub fn main_fetch(
         } else {
             false
         };
+        // Scripts running in a web origin must not read local files, even
+        // though other subresources still get to load them below.
+        let from_web_origin = match request.origin {
+            Origin::Origin(ImmutableOrigin::Tuple(..)) => true,
+            _ => false,
+        };
 
-        if (same_origin && !cors_flag ) ||
+        if current_url.scheme() == "file" &&
+            request.mode == RequestMode::CorsMode &&
+            from_web_origin
+        {
+            Response::network_error(NetworkError::Internal("Cross-origin file: access".into()))
+        } else if (same_origin && !cors_flag ) ||
             current_url.scheme() == "data" ||
             current_url.scheme() == "file" || // FIXME: Fetch spec has already dropped filtering against file:
                                               //        and about: schemes, but CSS tests will break on loading Ahem
@@ -444,11 +454,12 @@ pub fn main_fetch(
     };
 
     // Step 19.
-    let mut response_loaded = false;
     let mut response = if !response.is_network_error() && !request.integrity_metadata.is_empty() {
         // Step 19.1.
-        wait_for_response(&mut response, target, done_chan);
-        response_loaded = true;
+        // The body must not reach the target before the response itself, nor
+        // before it has been verified. Once received, it is sent as a single
+        // chunk by `wait_for_response` below.
+        wait_for_response_body(&mut response, done_chan);
 
         // Step 19.2.
         let ref integrity_metadata = &request.integrity_metadata;
@@ -470,9 +481,7 @@ pub fn main_fetch(
         // process_response is not supposed to be used
         // by sync fetch, but we overload it here for simplicity
         target.process_response(&mut response);
-        if !response_loaded {
-            wait_for_response(&mut response, target, done_chan);
-        }
+        wait_for_response(&mut response, target, done_chan);
         // overloaded similarly to process_response
         target.process_response_eof(&response);
         return response;
@@ -492,9 +501,7 @@ pub fn main_fetch(
     target.process_response(&response);
 
     // Step 23.
-    if !response_loaded {
-        wait_for_response(&mut response, target, done_chan);
-    }
+    wait_for_response(&mut response, target, done_chan);
 
     // Step 24.
     target.process_response_eof(&response);
@@ -508,6 +515,33 @@ pub fn main_fetch(
     response
 }
 
+/// Wait for the body of `response` to be fully received, without passing its
+/// chunks on to any target.
+fn wait_for_response_body(response: &mut Response, done_chan: &mut DoneChannel) {
+    if let Some(ref ch) = *done_chan {
+        loop {
+            match ch
+                .1
+                .recv()
+                .expect("fetch worker should always send Done before terminating")
+            {
+                Data::Payload(_) => {},
+                Data::Done => break,
+                Data::Cancelled => {
+                    response.aborted.store(true, Ordering::Release);
+                    break;
+                },
+                Data::Error(error) => {
+                    response.aborted.store(true, Ordering::Release);
+                    *response = Response::network_error(error);
+                    break;
+                },
+            }
+        }
+    }
+    *done_chan = None;
+}
+
 fn wait_for_response(response: &mut Response, target: Target, done_chan: &mut DoneChannel) {
     if let Some(ref ch) = *done_chan {
         loop {
@@ -524,6 +558,13 @@ fn wait_for_response(response: &mut Response, target: Target, done_chan: &mut Do
                     response.aborted.store(true, Ordering::Release);
                     break;
                 },
+                Data::Error(error) => {
+                    // Keep whatever was cached alongside this response from
+                    // being served later with a truncated body.
+                    response.aborted.store(true, Ordering::Release);
+                    *response = Response::network_error(error);
+                    break;
+                },
             }
         }
     } else {
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::connector::{create_http_client, Connector, TlsConfig};
+use crate::connector::{create_http_client, ConnectionInfo, Connector, TlsConfig};
 use crate::cookie;
 use crate::cookie_storage::CookieStorage;
 use crate::decoder::Decoder;
@@ -32,7 +32,7 @@ use headers::{
 };
 use http::header::{self, HeaderName, HeaderValue};
 use http::uri::Authority;
-use http::{HeaderMap, Request as HyperRequest};
+use http::{HeaderMap, Request as HyperRequest, Version};
 use hyper::{Body, Client, Method, Response as HyperResponse, StatusCode};
 use hyper_serde::Serde;
 use msg::constellation_msg::{HistoryStateId, PipelineId};
@@ -546,6 +546,7 @@ pub fn http_fetch(
 
     // Step 4
     if response.is_none() {
+        let mut cors_preflight_performed = false;
         // Substep 1
         if cors_preflight_flag {
             let method_cache_match = cache.match_method(&*request, request.method.clone());
@@ -560,6 +561,7 @@ pub fn http_fetch(
             // Sub-substep 1
             if method_mismatch || header_mismatch {
                 let preflight_result = cors_preflight_fetch(&request, cache, context);
+                cors_preflight_performed = true;
                 // Sub-substep 2
                 if let Some(e) = preflight_result.get_network_error() {
                     return Response::network_error(e.clone());
@@ -595,6 +597,7 @@ pub fn http_fetch(
         }
 
         fetch_result.return_internal = false;
+        fetch_result.cors_preflight_performed = cors_preflight_performed;
         response = Some(fetch_result);
     }
 
@@ -818,6 +821,20 @@ pub fn http_redirect_fetch(
         // TODO: extract request's body's source
     }
 
+    // Remember the redirect for the final response, before the URL changes.
+    let redirect = if request.record_redirects {
+        Some((
+            request.current_url(),
+            response
+                .actual_response()
+                .raw_status
+                .as_ref()
+                .map_or(0, |s| s.0),
+        ))
+    } else {
+        None
+    };
+
     // Step 13
     request.url_list.push(location_url);
 
@@ -827,7 +844,7 @@ pub fn http_redirect_fetch(
     // Step 15
     let recursive_flag = request.redirect_mode != RedirectMode::Manual;
 
-    let fetch_response = main_fetch(
+    let mut fetch_response = main_fetch(
         request,
         cache,
         cors_flag,
@@ -836,6 +853,12 @@ pub fn http_redirect_fetch(
         done_chan,
         context,
     );
+    if let Some(redirect) = redirect {
+        fetch_response.redirects.insert(0, redirect.clone());
+        if let Some(ref mut internal_response) = fetch_response.internal_response {
+            internal_response.redirects.insert(0, redirect);
+        }
+    }
 
     // TODO: timing allow check
     context
@@ -1199,7 +1222,7 @@ fn http_network_or_cache_fetch(
                 {
                     Data::Payload(_) => {},
                     Data::Done => break, // Return the full response as if it was initially cached as such.
-                    Data::Cancelled => {
+                    Data::Cancelled | Data::Error(_) => {
                         // The response was cancelled while the fetch was ongoing.
                         // Set response to None, which will trigger a network fetch below.
                         *response = None;
@@ -1483,11 +1506,31 @@ fn http_network_fetch(
         res.status().canonical_reason().unwrap_or("").into(),
     ));
     response.headers = res.headers().clone();
+    if request.record_connection_details {
+        response.http_version = Some(version_name(res.version()).to_owned());
+    }
+    if let Some(info) = res.extensions().get::<ConnectionInfo>() {
+        response.connection_reused = info.record_use();
+        if request.record_connection_details {
+            response.tls_info = info.tls_info();
+        }
+    }
     response.referrer = request.referrer.to_url().cloned();
     response.referrer_policy = request.referrer_policy.clone();
 
     let res_body = response.body.clone();
 
+    // The length the body must reach to be complete, when it is known. The
+    // Content-Length of an encoded body doesn't describe the decoded bytes
+    // we receive below, so it can't be checked then.
+    let expected_length = match response.headers.typed_get::<ContentEncoding>() {
+        Some(_) => None,
+        None => response
+            .headers
+            .typed_get::<ContentLength>()
+            .map(|length| length.0 as usize),
+    };
+
     // We're about to spawn a future to be waited on here
     let (done_sender, done_receiver) = unbounded();
     *done_chan = Some((done_sender.clone(), done_receiver));
@@ -1536,12 +1579,12 @@ fn http_network_fetch(
     let url2 = url1.clone();
     HANDLE.lock().unwrap().spawn(
         res.into_body()
-            .map_err(|_| ())
+            .map_err(Some)
             .fold(res_body, move |res_body, chunk| {
                 if cancellation_listener.lock().unwrap().cancelled() {
                     *res_body.lock().unwrap() = ResponseBody::Done(vec![]);
                     let _ = done_sender.send(Data::Cancelled);
-                    return future::failed(());
+                    return future::failed(None);
                 }
                 if let ResponseBody::Receiving(ref mut body) = *res_body.lock().unwrap() {
                     let bytes = chunk.into_bytes();
@@ -1565,19 +1608,31 @@ fn http_network_fetch(
                 let _ = done_sender2.send(Data::Done);
                 future::ok(())
             })
-            .map_err(move |_| {
+            .map_err(move |error| {
                 debug!("finished response for {:?} with error", url2);
                 let mut body = res_body2.lock().unwrap();
                 let completed_body = match *body {
                     ResponseBody::Receiving(ref mut body) => mem::replace(body, vec![]),
                     _ => vec![],
                 };
+                // A body that ends before its advertised Content-Length must
+                // not be passed off as complete. Other errors keep delivering
+                // what was received, as before.
+                let truncated = match (&error, expected_length) {
+                    (Some(_), Some(length)) => completed_body.len() < length,
+                    _ => false,
+                };
                 *body = ResponseBody::Done(completed_body);
                 timing_ptr3
                     .lock()
                     .unwrap()
                     .set_attribute(ResourceAttribute::ResponseEnd);
-                let _ = done_sender3.send(Data::Done);
+                let _ = done_sender3.send(match error {
+                    Some(ref error) if truncated => {
+                        Data::Error(NetworkError::from_hyper_error(error))
+                    },
+                    _ => Data::Done,
+                });
             }),
     );
 
@@ -1634,6 +1689,17 @@ fn http_network_fetch(
     response
 }
 
+/// The name of an HTTP version as it appears in a status line.
+fn version_name(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_11 => "HTTP/1.1",
+        Version::HTTP_2 => "HTTP/2",
+        _ => "HTTP",
+    }
+}
+
 /// [CORS preflight fetch](https://fetch.spec.whatwg.org#cors-preflight-fetch)
 fn cors_preflight_fetch(
     request: &Request,
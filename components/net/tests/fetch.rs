@@ -9,6 +9,7 @@ use crate::{
     create_embedder_proxy, fetch, make_server, make_ssl_server, new_fetch_context,
     DEFAULT_USER_AGENT,
 };
+use content_security_policy as csp;
 use crossbeam_channel::{unbounded, Sender};
 use devtools_traits::HttpRequest as DevtoolsHttpRequest;
 use devtools_traits::HttpResponse as DevtoolsHttpResponse;
@@ -227,6 +228,20 @@ fn test_fetch_file() {
     }
 }
 
+#[test]
+fn test_cors_fetch_file_from_web_origin() {
+    let path = Path::new("../../resources/servo.css")
+        .canonicalize()
+        .unwrap();
+    let url = ServoUrl::from_file_path(path).unwrap();
+    let origin = Origin::Origin(ServoUrl::parse("http://example.com").unwrap().origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.mode = RequestMode::CorsMode;
+
+    let fetch_response = fetch(&mut request, None);
+    assert!(fetch_response.is_network_error());
+}
+
 #[test]
 fn test_fetch_ftp() {
     let url = ServoUrl::parse("ftp://not-supported").unwrap();
@@ -297,6 +312,7 @@ fn test_cors_preflight_fetch() {
     let _ = server.close();
 
     assert!(!fetch_response.is_network_error());
+    assert!(fetch_response.cors_preflight_performed);
     match *fetch_response.body.lock().unwrap() {
         ResponseBody::Done(ref body) => assert_eq!(&**body, ACK),
         _ => panic!(),
@@ -355,6 +371,8 @@ fn test_cors_preflight_cache_fetch() {
 
     // The response from the CORS-preflight cache was used
     assert_eq!(1, counter.load(Ordering::SeqCst));
+    assert!(fetch_response0.cors_preflight_performed);
+    assert!(!fetch_response1.cors_preflight_performed);
 
     // The entry exists in the CORS-preflight cache
     assert_eq!(true, cache.match_method(&wrapped_request0, Method::GET));
@@ -598,6 +616,75 @@ fn test_fetch_response_is_opaque_redirect_filtered() {
     }
 }
 
+#[test]
+fn test_fetch_response_from_http_cache_has_local_cache_state() {
+    static MESSAGE: &'static [u8] = b"cached";
+    let state = Arc::new(AtomicUsize::new(0));
+    let counter = state.clone();
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        state.fetch_add(1, Ordering::SeqCst);
+        response
+            .headers_mut()
+            .typed_insert(Expires::from(SystemTime::now() + Duration::new(86400, 0)));
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let mut context = new_fetch_context(None, None);
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), None);
+    request.referrer = Referrer::NoReferrer;
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    match fetch_response.cache_state {
+        CacheState::None => {},
+        _ => panic!(),
+    }
+
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    let _ = server.close();
+
+    assert!(!fetch_response.is_network_error());
+    match fetch_response.cache_state {
+        CacheState::Local => {},
+        _ => panic!(),
+    }
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_fetch_reports_connection_reuse() {
+    static MESSAGE: &'static [u8] = b"not cached";
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+
+    let mut context = new_fetch_context(None, None);
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), None);
+    request.referrer = Referrer::NoReferrer;
+    request.record_connection_details = true;
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    assert!(!fetch_response.is_network_error());
+    assert!(!fetch_response.connection_reused);
+    assert!(fetch_response.tls_info.is_none());
+    assert_eq!(fetch_response.http_version, Some("HTTP/1.1".to_owned()));
+
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    let fetch_response = fetch_with_context(&mut request, &mut context);
+    let _ = server.close();
+
+    assert!(!fetch_response.is_network_error());
+    assert!(fetch_response.connection_reused);
+    assert_eq!(fetch_response.http_version, None);
+}
+
 #[test]
 fn test_fetch_with_local_urls_only() {
     // If flag `local_urls_only` is set, fetching a non-local URL must result in network error.
@@ -675,14 +762,46 @@ fn test_fetch_with_hsts() {
     request.referrer = Referrer::NoReferrer;
     // Set the flag.
     request.local_urls_only = false;
+    request.record_connection_details = true;
     let response = fetch_with_context(&mut request, &mut context);
     server.close();
+    let tls_info = response.tls_info.clone().expect("no TLS details for https");
+    assert!(tls_info.protocol.starts_with("TLSv1"));
     assert_eq!(
         response.internal_response.unwrap().url().unwrap().scheme(),
         "https"
     );
 }
 
+#[test]
+fn test_fetch_with_untrusted_certificate_reports_tls_failure() {
+    static MESSAGE: &'static [u8] = b"";
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        *response.body_mut() = MESSAGE.to_vec().into();
+    };
+
+    let cert_path = Path::new("../../resources/self_signed_certificate_for_testing.crt")
+        .canonicalize()
+        .unwrap();
+    let key_path = Path::new("../../resources/privatekey_for_testing.key")
+        .canonicalize()
+        .unwrap();
+    let (server, url) = make_ssl_server(handler, cert_path, key_path);
+    let url = ServoUrl::parse(&url.as_str().replacen("http", "https", 1)).unwrap();
+
+    // The default context doesn't trust the self-signed certificate.
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    let response = fetch(&mut request, None);
+    server.close();
+
+    match response.get_network_error() {
+        Some(NetworkError::Internal(reason)) => assert!(reason.contains("certificate")),
+        error => panic!("unexpected network error {:?}", error),
+    }
+}
+
 #[test]
 fn test_fetch_with_sri_network_error() {
     static MESSAGE: &'static [u8] = b"alert('Hello, Network Error');";
@@ -707,6 +826,31 @@ fn test_fetch_with_sri_network_error() {
     assert!(response.is_network_error());
 }
 
+#[test]
+fn test_fetch_blocked_by_csp() {
+    let handler = move |_: HyperRequest<Body>, _: &mut HyperResponse<Body>| {
+        panic!("the request should never reach the server");
+    };
+    let (server, url) = make_server(handler);
+
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.csp_list = Some(csp::CspList::parse(
+        "connect-src 'none'",
+        csp::PolicySource::Header,
+        csp::PolicyDisposition::Enforce,
+    ));
+
+    let response = fetch(&mut request, None);
+
+    let _ = server.close();
+    assert_eq!(
+        response.get_network_error(),
+        Some(&NetworkError::ContentSecurityPolicy)
+    );
+}
+
 #[test]
 fn test_fetch_with_sri_sucess() {
     static MESSAGE: &'static [u8] = b"alert('Hello, world.');";
@@ -842,6 +986,44 @@ fn test_fetch_redirect_count_failure() {
     };
 }
 
+#[test]
+fn test_fetch_redirects_are_recorded() {
+    let handler = move |request: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        let (status, location) = match request.uri().path() {
+            "/" => (StatusCode::MOVED_PERMANENTLY, "1"),
+            "/1" => (StatusCode::FOUND, "2"),
+            _ => return,
+        };
+        *response.status_mut() = status;
+        response
+            .headers_mut()
+            .insert(header::LOCATION, HeaderValue::from_static(location));
+    };
+    let (server, url) = make_server(handler);
+
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin.clone()), None);
+    request.referrer = Referrer::NoReferrer;
+    let fetch_response = fetch(&mut request, None);
+    assert!(!fetch_response.is_network_error());
+    assert!(fetch_response.redirects.is_empty());
+
+    let mut request = Request::new(url.clone(), Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.record_redirects = true;
+    let fetch_response = fetch(&mut request, None);
+    let _ = server.close();
+
+    assert!(!fetch_response.is_network_error());
+    let expected = vec![(url.clone(), 301), (url.join("1").unwrap(), 302)];
+    assert_eq!(fetch_response.redirects, expected);
+    assert_eq!(fetch_response.actual_response().redirects, expected);
+    assert_eq!(
+        fetch_response.actual_response().url(),
+        Some(&url.join("2").unwrap())
+    );
+}
+
 fn test_fetch_redirect_updates_method_runner(
     tx: Sender<bool>,
     status_code: StatusCode,
@@ -17,7 +17,7 @@ extern crate serde;
 
 use crate::filemanager_thread::FileManagerThreadMsg;
 use crate::request::{Request, RequestBuilder};
-use crate::response::{HttpsState, Response, ResponseInit};
+use crate::response::{CacheState, HttpsState, Response, ResponseInit};
 use crate::storage_thread::StorageThreadMsg;
 use cookie::Cookie;
 use headers::{ContentType, HeaderMapExt, ReferrerPolicy as ReferrerPolicyHeader};
@@ -272,9 +272,15 @@ impl<T: FetchResponseListener> Action<T> for FetchResponseMsg {
             FetchResponseMsg::ProcessResponseEOF(data) => {
                 match data {
                     Ok(ref response_resource_timing) => {
-                        // update listener with values from response
-                        *listener.resource_timing_mut() = response_resource_timing.clone();
-                        listener.process_response_eof(Ok(response_resource_timing.clone()));
+                        // update listener with values from response, keeping the
+                        // Server-Timing metrics the listener read from the headers
+                        let mut resource_timing = response_resource_timing.clone();
+                        if resource_timing.server_timing.is_empty() {
+                            resource_timing.server_timing =
+                                listener.resource_timing().server_timing.clone();
+                        }
+                        *listener.resource_timing_mut() = resource_timing.clone();
+                        listener.process_response_eof(Ok(resource_timing));
                         // TODO timing check https://w3c.github.io/resource-timing/#dfn-timing-allow-check
 
                         listener.submit_resource_timing();
@@ -476,6 +482,17 @@ pub struct ResourceFetchTiming {
     pub connect_start: u64,
     pub connect_end: u64,
     pub start_time: u64,
+    /// Metrics reported by the server through the `Server-Timing` header
+    pub server_timing: Vec<ServerTimingMetric>,
+}
+
+/// A single metric of a `Server-Timing` header.
+/// <https://w3c.github.io/server-timing/#the-server-timing-header-field>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct ServerTimingMetric {
+    pub name: String,
+    pub duration: f64,
+    pub description: String,
 }
 
 pub enum RedirectStartValue {
@@ -538,6 +555,7 @@ impl ResourceFetchTiming {
             connect_end: 0,
             response_end: 0,
             start_time: 0,
+            server_timing: vec![],
         }
     }
 
@@ -603,9 +621,19 @@ impl ResourceFetchTiming {
         self.redirect_start = 0;
         self.connect_start = 0;
         self.connect_end = 0;
+        self.server_timing.clear();
     }
 }
 
+/// Details of the TLS connection a response was received over.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct TlsInfo {
+    /// The negotiated protocol version, e.g. "TLSv1.2".
+    pub protocol: String,
+    /// The name of the negotiated cipher suite, if any.
+    pub cipher: Option<String>,
+}
+
 /// Metadata about a loaded resource, such as is obtained from HTTP headers.
 #[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
 pub struct Metadata {
@@ -639,6 +667,26 @@ pub struct Metadata {
     pub referrer_policy: Option<ReferrerPolicy>,
     /// Performance information for navigation events
     pub timing: Option<ResourceFetchTiming>,
+
+    /// [Cache state](https://fetch.spec.whatwg.org/#concept-response-cache-state)
+    /// of the response, i.e. whether it was served from the HTTP cache.
+    pub cache_state: CacheState,
+
+    /// Whether a CORS-preflight request was sent before the response was fetched.
+    pub cors_preflight_performed: bool,
+
+    /// Whether the response was received over a reused connection.
+    pub connection_reused: bool,
+
+    /// The TLS connection the response was received over, if any.
+    pub tls_info: Option<TlsInfo>,
+
+    /// The HTTP version of the response, if it came from the network.
+    pub http_version: Option<String>,
+
+    /// The URLs the request was redirected from, in order, each with the
+    /// status of its redirect response.
+    pub redirects: Vec<(ServoUrl, u16)>,
 }
 
 impl Metadata {
@@ -656,6 +704,12 @@ impl Metadata {
             referrer: None,
             referrer_policy: None,
             timing: None,
+            cache_state: CacheState::None,
+            cors_preflight_performed: false,
+            connection_reused: false,
+            tls_info: None,
+            http_version: None,
+            redirects: vec![],
         }
     }
 
@@ -695,11 +749,15 @@ pub enum NetworkError {
     LoadCancelled,
     /// SSL validation error that has to be handled in the HTML parser
     SslValidation(ServoUrl, String),
+    /// The request was blocked by the Content Security Policy of its client
+    ContentSecurityPolicy,
 }
 
 impl NetworkError {
     pub fn from_hyper_error(error: &HyperError) -> Self {
-        NetworkError::Internal(error.description().to_owned())
+        // The display form includes the underlying cause (DNS, TLS, connection
+        // failures), which the description alone doesn't.
+        NetworkError::Internal(error.to_string())
     }
 
     pub fn from_http_error(error: &HttpError) -> Self {
@@ -729,6 +787,83 @@ pub fn trim_http_whitespace(mut slice: &[u8]) -> &[u8] {
     slice
 }
 
+/// Parse the metrics of every `Server-Timing` header in `headers`, as described in
+/// <https://w3c.github.io/server-timing/#the-server-timing-header-field>.
+/// Malformed metrics without a name are skipped, and only the first `dur` and
+/// `desc` parameters of each metric are taken into account.
+pub fn parse_server_timing(headers: &HeaderMap) -> Vec<ServerTimingMetric> {
+    headers
+        .get_all("server-timing")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| split_unquoted(value, ','))
+        .filter_map(|metric| {
+            let mut parts = split_unquoted(metric, ';').into_iter();
+            let name = parts.next()?.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let mut duration = None;
+            let mut description = None;
+            for param in parts {
+                let mut param = param.splitn(2, '=');
+                let key = param.next().unwrap_or("").trim();
+                let value = unquote(param.next().unwrap_or("").trim());
+                if key.eq_ignore_ascii_case("dur") && duration.is_none() {
+                    duration = Some(value.parse().unwrap_or(0.));
+                } else if key.eq_ignore_ascii_case("desc") && description.is_none() {
+                    description = Some(value);
+                }
+            }
+            Some(ServerTimingMetric {
+                name: name.to_owned(),
+                duration: duration.unwrap_or(0.),
+                description: description.unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Split `value` on every `separator` that is not part of a quoted-string.
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c == separator && !in_quotes => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Remove the surrounding quotes and escapes of a quoted-string, if any.
+fn unquote(value: &str) -> String {
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return value.to_owned();
+    }
+    let mut unquoted = String::with_capacity(value.len() - 2);
+    let mut escaped = false;
+    for c in value[1..value.len() - 1].chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else {
+            unquoted.push(c);
+            escaped = false;
+        }
+    }
+    unquoted
+}
+
 pub fn http_percent_encode(bytes: &[u8]) -> String {
     // This encode set is used for HTTP header values and is defined at
     // https://tools.ietf.org/html/rfc5987#section-3.2
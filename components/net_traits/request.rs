@@ -146,6 +146,7 @@ pub struct RequestBuilder {
     pub pipeline_id: Option<PipelineId>,
     pub redirect_mode: RedirectMode,
     pub integrity_metadata: String,
+    pub keep_alive: bool,
     // This is nominally a part of the client's global object.
     // It is copied here to avoid having to reach across the thread
     // boundary every time a redirect occurs.
@@ -155,6 +156,8 @@ pub struct RequestBuilder {
     pub url_list: Vec<ServoUrl>,
     pub parser_metadata: ParserMetadata,
     pub initiator: Initiator,
+    pub record_connection_details: bool,
+    pub record_redirects: bool,
 }
 
 impl RequestBuilder {
@@ -179,10 +182,13 @@ impl RequestBuilder {
             pipeline_id: None,
             redirect_mode: RedirectMode::Follow,
             integrity_metadata: "".to_owned(),
+            keep_alive: false,
             url_list: vec![],
             parser_metadata: ParserMetadata::Default,
             initiator: Initiator::None,
             csp_list: None,
+            record_connection_details: false,
+            record_redirects: false,
         }
     }
 
@@ -271,11 +277,26 @@ impl RequestBuilder {
         self
     }
 
+    pub fn keep_alive(mut self, keep_alive: bool) -> RequestBuilder {
+        self.keep_alive = keep_alive;
+        self
+    }
+
     pub fn parser_metadata(mut self, parser_metadata: ParserMetadata) -> RequestBuilder {
         self.parser_metadata = parser_metadata;
         self
     }
 
+    pub fn record_connection_details(mut self, record: bool) -> RequestBuilder {
+        self.record_connection_details = record;
+        self
+    }
+
+    pub fn record_redirects(mut self, record: bool) -> RequestBuilder {
+        self.record_redirects = record;
+        self
+    }
+
     pub fn build(self) -> Request {
         let mut request = Request::new(
             self.url.clone(),
@@ -305,8 +326,11 @@ impl RequestBuilder {
         request.redirect_count = url_list.len() as u32 - 1;
         request.url_list = url_list;
         request.integrity_metadata = self.integrity_metadata;
+        request.keep_alive = self.keep_alive;
         request.parser_metadata = self.parser_metadata;
         request.csp_list = self.csp_list;
+        request.record_connection_details = self.record_connection_details;
+        request.record_redirects = self.record_redirects;
         request
     }
 }
@@ -379,6 +403,12 @@ pub struct Request {
     // boundary every time a redirect occurs.
     #[ignore_malloc_size_of = "Defined in rust-content-security-policy"]
     pub csp_list: Option<CspList>,
+    /// Whether the response should carry details of the connection it was
+    /// received over, which only embedders ask for.
+    pub record_connection_details: bool,
+    /// Whether the response should record the URLs the request was
+    /// redirected from, which only embedders ask for.
+    pub record_redirects: bool,
 }
 
 impl Request {
@@ -412,6 +442,8 @@ impl Request {
             redirect_count: 0,
             response_tainting: ResponseTainting::Basic,
             csp_list: None,
+            record_connection_details: false,
+            record_redirects: false,
         }
     }
 
@@ -5,7 +5,7 @@
 //! The [Response](https://fetch.spec.whatwg.org/#responses) object
 //! resulting from a [fetch operation](https://fetch.spec.whatwg.org/#concept-fetch)
 use crate::{FetchMetadata, FilteredMetadata, Metadata, NetworkError, ReferrerPolicy};
-use crate::{ResourceFetchTiming, ResourceTimingType};
+use crate::{ResourceFetchTiming, ResourceTimingType, TlsInfo};
 use headers::{AccessControlExposeHeaders, ContentType, HeaderMapExt};
 use http::{HeaderMap, StatusCode};
 use hyper_serde::Serde;
@@ -89,6 +89,9 @@ pub struct Response {
     pub termination_reason: Option<TerminationReason>,
     url: Option<ServoUrl>,
     pub url_list: Vec<ServoUrl>,
+    /// The URLs the request was redirected from, in order, each with the
+    /// status of its redirect response.
+    pub redirects: Vec<(ServoUrl, u16)>,
     /// `None` can be considered a StatusCode of `0`.
     #[ignore_malloc_size_of = "Defined in hyper"]
     pub status: Option<(StatusCode, String)>,
@@ -98,6 +101,16 @@ pub struct Response {
     #[ignore_malloc_size_of = "Mutex heap size undefined"]
     pub body: Arc<Mutex<ResponseBody>>,
     pub cache_state: CacheState,
+    /// Whether a CORS-preflight request was sent before this response was fetched.
+    pub cors_preflight_performed: bool,
+    /// Whether the response was received over a connection opened for an
+    /// earlier request.
+    pub connection_reused: bool,
+    /// The TLS connection the response was received over, if any.
+    pub tls_info: Option<TlsInfo>,
+    /// The HTTP version of the response, e.g. "HTTP/1.1", if it came from
+    /// the network.
+    pub http_version: Option<String>,
     pub https_state: HttpsState,
     pub referrer: Option<ServoUrl>,
     pub referrer_policy: Option<ReferrerPolicy>,
@@ -125,11 +138,16 @@ impl Response {
             termination_reason: None,
             url: Some(url),
             url_list: vec![],
+            redirects: vec![],
             status: Some((StatusCode::OK, "".to_string())),
             raw_status: Some((200, b"".to_vec())),
             headers: HeaderMap::new(),
             body: Arc::new(Mutex::new(ResponseBody::Empty)),
             cache_state: CacheState::None,
+            cors_preflight_performed: false,
+            connection_reused: false,
+            tls_info: None,
+            http_version: None,
             https_state: HttpsState::None,
             referrer: None,
             referrer_policy: None,
@@ -159,11 +177,16 @@ impl Response {
             termination_reason: None,
             url: None,
             url_list: vec![],
+            redirects: vec![],
             status: None,
             raw_status: None,
             headers: HeaderMap::new(),
             body: Arc::new(Mutex::new(ResponseBody::Empty)),
             cache_state: CacheState::None,
+            cors_preflight_performed: false,
+            connection_reused: false,
+            tls_info: None,
+            http_version: None,
             https_state: HttpsState::None,
             referrer: None,
             referrer_policy: None,
@@ -278,6 +301,7 @@ impl Response {
 
             ResponseType::Opaque => {
                 response.url_list = vec![];
+                response.redirects = vec![];
                 response.url = None;
                 response.headers = HeaderMap::new();
                 response.status = None;
@@ -312,6 +336,12 @@ impl Response {
             metadata.https_state = response.https_state;
             metadata.referrer = response.referrer.clone();
             metadata.referrer_policy = response.referrer_policy.clone();
+            metadata.cache_state = response.cache_state.clone();
+            metadata.cors_preflight_performed = response.cors_preflight_performed;
+            metadata.connection_reused = response.connection_reused;
+            metadata.tls_info = response.tls_info.clone();
+            metadata.http_version = response.http_version.clone();
+            metadata.redirects = response.redirects.clone();
             metadata
         };
 
@@ -2,6 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use http::header::{HeaderMap, HeaderValue};
+use net_traits::{parse_server_timing, ServerTimingMetric};
 use net_traits::{ResourceAttribute, ResourceFetchTiming, ResourceTimeValue, ResourceTimingType};
 
 #[test]
@@ -210,3 +212,39 @@ fn test_reset_start_time() {
         "failed to reset `start_time`"
     );
 }
+
+#[test]
+fn test_parse_server_timing() {
+    let mut headers = HeaderMap::new();
+    headers.insert("server-timing", HeaderValue::from_static("db;dur=53"));
+    assert_eq!(
+        parse_server_timing(&headers),
+        vec![ServerTimingMetric {
+            name: "db".to_owned(),
+            duration: 53.,
+            description: "".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn test_parse_server_timing_multiple_metrics() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "server-timing",
+        HeaderValue::from_static("cache;desc=\"hit, warm\";dur=1.5, ;dur=2, app;dur=7;dur=8"),
+    );
+    headers.append("server-timing", HeaderValue::from_static("miss"));
+    let metrics: Vec<(String, f64, String)> = parse_server_timing(&headers)
+        .into_iter()
+        .map(|m| (m.name, m.duration, m.description))
+        .collect();
+    assert_eq!(
+        metrics,
+        vec![
+            ("cache".to_owned(), 1.5, "hit, warm".to_owned()),
+            ("app".to_owned(), 7., "".to_owned()),
+            ("miss".to_owned(), 0., "".to_owned()),
+        ]
+    );
+}
@@ -40,6 +40,8 @@ use crate::dom::document::PendingRestyle;
 use crate::dom::htmlimageelement::SourceSet;
 use crate::dom::htmlmediaelement::{HTMLMediaElementFetchContext, MediaFrameRenderer};
 use crate::dom::identityhub::Identities;
+use crate::dom::xmlhttprequest::{CompletionObserver, ReadyStateObserver};
+use crate::dom::xmlhttprequest::{RequestStartObserver, ResponseSink, UrlRewriter};
 use crate::script_runtime::StreamConsumer;
 use crate::task::TaskBox;
 use app_units::Au;
@@ -65,7 +67,7 @@ use euclid::default::{Point2D, Rect, Rotation3D, Transform2D, Transform3D};
 use euclid::Length as EuclidLength;
 use html5ever::buffer_queue::BufferQueue;
 use html5ever::{LocalName, Namespace, Prefix, QualName};
-use http::header::HeaderMap;
+use http::header::{HeaderMap, HeaderName};
 use hyper::Method;
 use hyper::StatusCode;
 use indexmap::IndexMap;
@@ -159,6 +161,13 @@ pub unsafe trait JSTraceable {
 }
 
 unsafe_no_jsmanaged_fields!(Box<dyn TaskBox>, Box<dyn EventLoopWaker>);
+unsafe_no_jsmanaged_fields!(
+    Box<dyn CompletionObserver>,
+    Box<dyn ReadyStateObserver>,
+    Box<dyn RequestStartObserver>,
+    Box<dyn ResponseSink>,
+    Box<dyn UrlRewriter>
+);
 
 unsafe_no_jsmanaged_fields!(MessagePortImpl);
 unsafe_no_jsmanaged_fields!(MessagePortId);
@@ -446,7 +455,7 @@ unsafe_no_jsmanaged_fields!(TimelineMarkerType);
 unsafe_no_jsmanaged_fields!(WorkerId);
 unsafe_no_jsmanaged_fields!(BufferQueue, QuirksMode, StrTendril);
 unsafe_no_jsmanaged_fields!(Runtime);
-unsafe_no_jsmanaged_fields!(HeaderMap, Method);
+unsafe_no_jsmanaged_fields!(HeaderMap, HeaderName, Method);
 unsafe_no_jsmanaged_fields!(WindowProxyHandler);
 unsafe_no_jsmanaged_fields!(UntrustedNodeAddress, OpaqueNode);
 unsafe_no_jsmanaged_fields!(LengthOrPercentageOrAuto);
@@ -313,6 +313,13 @@ impl<T: WeakReferenceable> DOMTracker<T> {
         self.dom_objects.borrow_mut().push(WeakRef::new(dom_object));
     }
 
+    pub fn untrack(&self, dom_object: &T) {
+        let mut dom_objects = self.dom_objects.borrow_mut();
+        if let Some(position) = dom_objects.iter().position(|ref_| ref_ == dom_object) {
+            dom_objects.swap_remove(position);
+        }
+    }
+
     pub fn for_each<F: FnMut(DomRoot<T>)>(&self, mut f: F) {
         self.dom_objects.borrow_mut().update(|weak_ref| {
             let root = weak_ref.root().unwrap();
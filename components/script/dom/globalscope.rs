@@ -6,6 +6,7 @@ use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::EventSourceBinding::EventSourceBinding::EventSourceMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::Bindings::WorkerGlobalScopeBinding::WorkerGlobalScopeMethods;
+use crate::dom::bindings::codegen::Bindings::XMLHttpRequestBinding::XMLHttpRequestMethods;
 use crate::dom::bindings::conversions::{root_from_object, root_from_object_static};
 use crate::dom::bindings::error::{report_pending_exception, ErrorInfo};
 use crate::dom::bindings::inheritance::Castable;
@@ -29,6 +30,7 @@ use crate::dom::performance::Performance;
 use crate::dom::window::Window;
 use crate::dom::workerglobalscope::WorkerGlobalScope;
 use crate::dom::workletglobalscope::WorkletGlobalScope;
+use crate::dom::xmlhttprequest::XMLHttpRequest;
 use crate::microtask::{Microtask, MicrotaskQueue};
 use crate::script_runtime::{CommonScriptMsg, JSContext as SafeJSContext, ScriptChan, ScriptPort};
 use crate::script_thread::{MainThreadScriptChan, ScriptThread};
@@ -161,6 +163,9 @@ pub struct GlobalScope {
     /// Vector storing references of all eventsources.
     event_source_tracker: DOMTracker<EventSource>,
 
+    /// Vector storing references of the XHRs with a request in flight.
+    xhr_tracker: DOMTracker<XMLHttpRequest>,
+
     /// Storage for watching rejected promises waiting for some client to
     /// consume their rejection.
     /// Promises in this list have been rejected in the last turn of the
@@ -364,6 +369,7 @@ impl GlobalScope {
             microtask_queue,
             list_auto_close_worker: Default::default(),
             event_source_tracker: DOMTracker::new(),
+            xhr_tracker: DOMTracker::new(),
             uncaught_rejections: Default::default(),
             consumed_rejections: Default::default(),
             is_headless,
@@ -839,6 +845,31 @@ impl GlobalScope {
         canceled_any_fetch
     }
 
+    pub fn track_xhr(&self, xhr: &XMLHttpRequest) {
+        self.xhr_tracker.track(xhr);
+    }
+
+    pub fn untrack_xhr(&self, xhr: &XMLHttpRequest) {
+        self.xhr_tracker.untrack(xhr);
+    }
+
+    /// Abort every XHR of this global that has a request in flight, firing
+    /// their `abort` events. Returns whether any request was aborted.
+    pub fn abort_xhrs(&self) -> bool {
+        // The event handlers may create new XHRs, so don't abort while
+        // iterating over the tracker.
+        let mut in_flight = vec![];
+        self.xhr_tracker.for_each(|xhr: DomRoot<XMLHttpRequest>| {
+            if xhr.in_flight() {
+                in_flight.push(xhr);
+            }
+        });
+        for xhr in &in_flight {
+            xhr.Abort();
+        }
+        !in_flight.is_empty()
+    }
+
     /// Returns the global scope of the realm that the given DOM object's reflector
     /// was created in.
     #[allow(unsafe_code)]
@@ -480,7 +480,6 @@ impl HTMLFormElement {
         encoding: &'static Encoding,
         target: &Window,
     ) {
-        let boundary = generate_boundary();
         let bytes = match enctype {
             FormEncType::UrlEncoded => {
                 let charset = encoding.name();
@@ -499,11 +498,12 @@ impl HTMLFormElement {
                 url.query().unwrap_or("").to_string().into_bytes()
             },
             FormEncType::FormDataEncoded => {
+                let (bytes, boundary) = encode_multipart_form_data(form_data, encoding);
                 let mime: Mime = format!("multipart/form-data; boundary={}", boundary)
                     .parse()
                     .unwrap();
                 load_data.headers.typed_insert(ContentType::from(mime));
-                encode_multipart_form_data(form_data, boundary, encoding)
+                bytes
             },
             FormEncType::TextPlainEncoded => {
                 load_data
@@ -1294,12 +1294,38 @@ impl FormControlElementHelpers for Element {
     }
 }
 
-// https://html.spec.whatwg.org/multipage/#multipart/form-data-encoding-algorithm
+/// Encodes `form_data` with a boundary that occurs in none of its entries, or
+/// the receiver would take it for the delimiter of the next part. Returns the
+/// body along with its boundary.
 pub fn encode_multipart_form_data(
     form_data: &mut Vec<FormDatum>,
-    boundary: String,
     encoding: &'static Encoding,
-) -> Vec<u8> {
+) -> (Vec<u8>, String) {
+    loop {
+        let boundary = generate_boundary();
+        // The entries are checked as they're encoded, so that files are only
+        // read again in the unlikely case that the boundary occurs in one.
+        if let Some(bytes) =
+            encode_multipart_form_data_with_boundary(form_data, &boundary, encoding)
+        {
+            return (bytes, boundary);
+        }
+    }
+}
+
+// https://html.spec.whatwg.org/multipage/#multipart/form-data-encoding-algorithm
+/// `None` if `boundary` occurs in one of the entries.
+fn encode_multipart_form_data_with_boundary(
+    form_data: &mut Vec<FormDatum>,
+    boundary: &str,
+    encoding: &'static Encoding,
+) -> Option<Vec<u8>> {
+    let contains = |haystack: &[u8]| {
+        haystack
+            .windows(boundary.len())
+            .any(|window| window == boundary.as_bytes())
+    };
+
     // Step 1
     let mut result = vec![];
 
@@ -1314,6 +1340,10 @@ pub fn encode_multipart_form_data(
         }
         // TODO: 3.2
 
+        if contains(entry.name.as_bytes()) {
+            return None;
+        }
+
         // Step 4
         // https://tools.ietf.org/html/rfc7578#section-4
         // NOTE(izgzhen): The encoding here expected by most servers seems different from
@@ -1325,6 +1355,9 @@ pub fn encode_multipart_form_data(
         // are capable of it.
         match entry.value {
             FormDatumValue::String(ref s) => {
+                if contains(s.as_bytes()) {
+                    return None;
+                }
                 let content_disposition = format!("form-data; name=\"{}\"", entry.name);
                 let mut bytes =
                     format!("Content-Disposition: {}\r\n\r\n{}", content_disposition, s)
@@ -1332,6 +1365,9 @@ pub fn encode_multipart_form_data(
                 result.append(&mut bytes);
             },
             FormDatumValue::File(ref f) => {
+                if contains(f.name().as_bytes()) {
+                    return None;
+                }
                 let extra = if charset.to_lowercase() == "utf-8" {
                     format!(
                         "filename=\"{}\"",
@@ -1360,6 +1396,9 @@ pub fn encode_multipart_form_data(
                 result.append(&mut type_bytes);
 
                 let mut bytes = f.upcast::<Blob>().get_bytes().unwrap_or(vec![]);
+                if contains(&bytes) {
+                    return None;
+                }
 
                 result.append(&mut bytes);
             },
@@ -1369,7 +1408,7 @@ pub fn encode_multipart_form_data(
     let mut boundary_bytes = format!("\r\n--{}--\r\n", boundary).into_bytes();
     result.append(&mut boundary_bytes);
 
-    result
+    Some(result)
 }
 
 // https://tools.ietf.org/html/rfc7578#section-4.1
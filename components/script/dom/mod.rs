@@ -443,6 +443,7 @@ pub mod performanceobserver;
 pub mod performanceobserverentrylist;
 pub mod performancepainttiming;
 pub mod performanceresourcetiming;
+pub mod performanceservertiming;
 pub mod permissions;
 pub mod permissionstatus;
 pub mod plugin;
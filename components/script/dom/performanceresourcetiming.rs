@@ -8,11 +8,15 @@ use crate::dom::bindings::codegen::Bindings::PerformanceResourceTimingBinding::{
 };
 use crate::dom::bindings::num::Finite;
 use crate::dom::bindings::reflector::reflect_dom_object;
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::utils::to_frozen_array;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::performanceentry::PerformanceEntry;
+use crate::dom::performanceservertiming::PerformanceServerTiming;
+use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
+use js::jsval::JSVal;
 use net_traits::ResourceFetchTiming;
 use servo_url::ServoUrl;
 
@@ -53,6 +57,7 @@ pub struct PerformanceResourceTiming {
     transfer_size: u64,     //size in octets
     encoded_body_size: u64, //size in octets
     decoded_body_size: u64, //size in octets
+    server_timing: Vec<Dom<PerformanceServerTiming>>,
 }
 
 // TODO(#21269): next_hop
@@ -98,6 +103,7 @@ impl PerformanceResourceTiming {
             transfer_size: 0,
             encoded_body_size: 0,
             decoded_body_size: 0,
+            server_timing: vec![],
         }
     }
 
@@ -108,6 +114,7 @@ impl PerformanceResourceTiming {
         initiator_type: InitiatorType,
         next_hop: Option<DOMString>,
         resource_timing: &ResourceFetchTiming,
+        server_timing: &[DomRoot<PerformanceServerTiming>],
     ) -> PerformanceResourceTiming {
         PerformanceResourceTiming {
             entry: PerformanceEntry::new_inherited(
@@ -134,6 +141,7 @@ impl PerformanceResourceTiming {
             transfer_size: 0,
             encoded_body_size: 0,
             decoded_body_size: 0,
+            server_timing: server_timing.iter().map(|t| Dom::from_ref(&**t)).collect(),
         }
     }
 
@@ -144,12 +152,18 @@ impl PerformanceResourceTiming {
         next_hop: Option<DOMString>,
         resource_timing: &ResourceFetchTiming,
     ) -> DomRoot<PerformanceResourceTiming> {
+        let server_timing: Vec<DomRoot<PerformanceServerTiming>> = resource_timing
+            .server_timing
+            .iter()
+            .map(|metric| PerformanceServerTiming::new(global, metric))
+            .collect();
         reflect_dom_object(
             Box::new(PerformanceResourceTiming::from_resource_timing(
                 url,
                 initiator_type,
                 next_hop,
                 resource_timing,
+                &server_timing,
             )),
             global,
             PerformanceResourceTimingBinding::Wrap,
@@ -249,4 +263,14 @@ impl PerformanceResourceTimingMethods for PerformanceResourceTiming {
     fn ResponseEnd(&self) -> DOMHighResTimeStamp {
         Finite::wrap(self.response_end)
     }
+
+    // https://w3c.github.io/server-timing/#dom-performanceresourcetiming-servertiming
+    fn ServerTiming(&self, cx: JSContext) -> JSVal {
+        let server_timing: Vec<DomRoot<PerformanceServerTiming>> = self
+            .server_timing
+            .iter()
+            .map(|timing| DomRoot::from_ref(&**timing))
+            .collect();
+        to_frozen_array(&server_timing, cx)
+    }
 }
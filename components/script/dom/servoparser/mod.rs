@@ -723,8 +723,12 @@ impl FetchResponseListener for ParserContext {
                 meta.set_content_type(mime.as_ref());
                 Some(meta)
             },
-            Err(NetworkError::Internal(reason)) => {
-                network_error = Some(reason);
+            Err(error @ NetworkError::Internal(_)) |
+            Err(error @ NetworkError::ContentSecurityPolicy) => {
+                network_error = Some(match error {
+                    NetworkError::Internal(reason) => reason,
+                    _ => "Blocked by Content-Security-Policy".to_owned(),
+                });
                 let mut meta = Metadata::default(self.url.clone());
                 let mime: Option<Mime> = "text/html".parse().ok();
                 meta.set_content_type(mime.as_ref());
@@ -26,7 +26,7 @@ use crate::dom::eventtarget::EventTarget;
 use crate::dom::formdata::FormData;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::headers::is_forbidden_header_name;
-use crate::dom::htmlformelement::{encode_multipart_form_data, generate_boundary};
+use crate::dom::htmlformelement::encode_multipart_form_data;
 use crate::dom::node::Node;
 use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::progressevent::ProgressEvent;
@@ -39,12 +39,16 @@ use crate::dom::xmlhttprequestupload::XMLHttpRequestUpload;
 use crate::fetch::FetchCanceller;
 use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
 use crate::script_runtime::JSContext;
+use crate::task::TaskCanceller;
 use crate::task_source::networking::NetworkingTaskSource;
-use crate::task_source::TaskSourceName;
+use crate::task_source::{TaskSource, TaskSourceName};
 use crate::timers::{OneshotTimerCallback, OneshotTimerHandle};
+use crossbeam_channel::{unbounded, Receiver, Sender};
 use dom_struct::dom_struct;
 use encoding_rs::{Encoding, UTF_8};
 use euclid::Length;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use headers::{ContentLength, ContentType, HeaderMapExt};
 use html5ever::serialize;
 use html5ever::serialize::SerializeOpts;
@@ -59,24 +63,33 @@ use js::jsval::{JSVal, NullValue, UndefinedValue};
 use js::rust::wrappers::JS_ParseJSON;
 use js::typedarray::{ArrayBuffer, CreateWith};
 use mime::{self, Mime, Name};
-use net_traits::request::{CredentialsMode, Destination, Referrer, RequestBuilder, RequestMode};
-use net_traits::trim_http_whitespace;
+use net_traits::request::{CredentialsMode, Destination, RedirectMode, Referrer};
+use net_traits::request::{RequestBuilder, RequestMode};
+use net_traits::response::CacheState;
 use net_traits::CoreResourceMsg::Fetch;
+use net_traits::{parse_server_timing, trim_http_whitespace};
 use net_traits::{FetchChannels, FetchMetadata, FilteredMetadata};
-use net_traits::{FetchResponseListener, NetworkError, ReferrerPolicy};
-use net_traits::{ResourceFetchTiming, ResourceTimingType};
+use net_traits::{FetchResponseListener, Metadata, NetworkError, ReferrerPolicy};
+use net_traits::{ResourceFetchTiming, ResourceTimingType, TlsInfo};
 use script_traits::DocumentActivity;
 use servo_atoms::Atom;
+use servo_config::pref;
 use servo_url::ServoUrl;
 use std::borrow::ToOwned;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::cmp;
+use std::collections::HashMap;
 use std::default::Default;
+use std::io::Write;
+use std::mem;
 use std::ptr;
 use std::ptr::NonNull;
+use std::rc::Rc;
 use std::slice;
 use std::str::{self, FromStr};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use url::Position;
 
 #[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
@@ -91,6 +104,245 @@ enum XMLHttpRequestState {
 #[derive(Clone, Copy, JSTraceable, MallocSizeOf, PartialEq)]
 pub struct GenerationId(u32);
 
+/// <https://fetch.spec.whatwg.org/#http-network-or-cache-fetch>, step 5.8.4
+const KEEP_ALIVE_BODY_LIMIT: usize = 64 * 1024;
+
+/// How often a throttled response body is released, see
+/// `XMLHttpRequest::set_bandwidth_limit`.
+const THROTTLE_INTERVAL_MS: u64 = 100;
+
+/// An embedder hook notified of every `readyState` change of an XHR.
+/// Observers must not register further observers from the callback.
+pub trait ReadyStateObserver {
+    fn ready_state_changed(&self, xhr: &XMLHttpRequest, state: u16);
+}
+
+/// Records the `readyState` sequence of an XHR, for testing.
+struct ReadyStateRecorder;
+
+impl ReadyStateObserver for ReadyStateRecorder {
+    fn ready_state_changed(&self, xhr: &XMLHttpRequest, state: u16) {
+        xhr.recorded_ready_states.borrow_mut().push(state);
+    }
+}
+
+/// An embedder hook notified exactly once when a request ends, right before
+/// `readyState` becomes `DONE`.
+pub trait CompletionObserver {
+    /// The response was fully received. `headers` are filtered the same way
+    /// as for script, and `length` is the size of the response body.
+    fn request_completed(
+        &self,
+        xhr: &XMLHttpRequest,
+        status: u16,
+        status_text: &[u8],
+        headers: &HeaderMap,
+        length: usize,
+    );
+
+    /// The request was aborted, timed out or failed with a network error.
+    fn request_failed(&self, xhr: &XMLHttpRequest, error: &Error);
+}
+
+/// Records the completion of the requests of an XHR, for testing.
+struct CompletionRecorder;
+
+impl CompletionObserver for CompletionRecorder {
+    fn request_completed(
+        &self,
+        xhr: &XMLHttpRequest,
+        status: u16,
+        status_text: &[u8],
+        headers: &HeaderMap,
+        length: usize,
+    ) {
+        let record = format!(
+            "completed {} {} {} headers {} bytes",
+            status,
+            String::from_utf8_lossy(status_text),
+            headers.len(),
+            length
+        );
+        xhr.recorded_completions.borrow_mut().push(record);
+    }
+
+    fn request_failed(&self, xhr: &XMLHttpRequest, error: &Error) {
+        let record = match *error {
+            Error::Abort => "failed abort",
+            Error::Timeout => "failed timeout",
+            _ => "failed error",
+        };
+        xhr.recorded_completions
+            .borrow_mut()
+            .push(record.to_owned());
+    }
+}
+
+/// An embedder hook that sends requests to a different URL than the one they
+/// were opened with, e.g. to route them to a mock server, without script
+/// noticing. `responseURL` still reflects the URL the response came from.
+pub trait UrlRewriter {
+    /// Returns the URL to fetch instead of `url`, if any.
+    fn rewrite_url(&self, url: &ServoUrl) -> Option<ServoUrl>;
+}
+
+/// Rewrites URLs starting with a given prefix, for testing.
+struct PrefixRewriter {
+    from: String,
+    to: String,
+}
+
+impl UrlRewriter for PrefixRewriter {
+    fn rewrite_url(&self, url: &ServoUrl) -> Option<ServoUrl> {
+        if !url.as_str().starts_with(&self.from) {
+            return None;
+        }
+        ServoUrl::parse(&format!("{}{}", self.to, &url.as_str()[self.from.len()..])).ok()
+    }
+}
+
+/// An embedder hook notified right before a request is handed to the network,
+/// e.g. to write HAR logs. `headers` are the ones the XHR sends; the network
+/// stack may still add others, such as cookies. Values of the headers passed
+/// to `XMLHttpRequest::set_redacted_request_headers` are replaced with
+/// `REDACTED_HEADER_VALUE`.
+pub trait RequestStartObserver {
+    fn request_started(
+        &self,
+        xhr: &XMLHttpRequest,
+        method: &Method,
+        url: &ServoUrl,
+        headers: &HeaderMap,
+    );
+}
+
+/// What request start observers see instead of the value of a redacted header.
+pub const REDACTED_HEADER_VALUE: &str = "[redacted]";
+
+/// Records the requests an XHR starts, for testing.
+struct RequestStartRecorder;
+
+impl RequestStartObserver for RequestStartRecorder {
+    fn request_started(
+        &self,
+        xhr: &XMLHttpRequest,
+        method: &Method,
+        url: &ServoUrl,
+        headers: &HeaderMap,
+    ) {
+        let mut names: Vec<&str> = headers.keys().map(HeaderName::as_str).collect();
+        names.sort();
+        let record = format!("{} {} {}", method, url, names.join(","));
+        xhr.recorded_request_starts.borrow_mut().push(record);
+        *xhr.recorded_request_headers.borrow_mut() = headers.clone();
+    }
+}
+
+/// An embedder sink receiving a copy of the response body as it arrives,
+/// e.g. to cache a download while script consumes it. A sink added while a
+/// request is ongoing only sees the chunks that arrive afterwards. It is
+/// dropped once that request ends, or when the XHR is reset.
+pub trait ResponseSink {
+    /// A chunk of the body, in the order it was received.
+    fn write(&self, xhr: &XMLHttpRequest, chunk: &[u8]);
+
+    /// The whole body was received.
+    fn finish(&self, xhr: &XMLHttpRequest);
+
+    /// The request was aborted, timed out or failed with a network error, or
+    /// the XHR was reset before it ended.
+    fn fail(&self, xhr: &XMLHttpRequest, error: &Error);
+}
+
+/// Collects a copy of the response body, for testing.
+struct ResponseTee;
+
+impl ResponseSink for ResponseTee {
+    fn write(&self, xhr: &XMLHttpRequest, chunk: &[u8]) {
+        xhr.tee_buffer.borrow_mut().extend_from_slice(chunk);
+    }
+
+    fn finish(&self, xhr: &XMLHttpRequest) {
+        let body = mem::replace(&mut *xhr.tee_buffer.borrow_mut(), vec![]);
+        *xhr.teed_response.borrow_mut() = Some(body);
+        xhr.teeing.set(false);
+    }
+
+    fn fail(&self, xhr: &XMLHttpRequest, _error: &Error) {
+        xhr.tee_buffer.borrow_mut().clear();
+        xhr.teeing.set(false);
+    }
+}
+
+/// A message of the stream returned by `XMLHttpRequest::response_stream`.
+pub enum ResponseStreamMsg {
+    /// A chunk of the body, in the order it was received.
+    Chunk(Vec<u8>),
+    /// The whole body was received. This is the last message.
+    Done,
+    /// The request was aborted, timed out or failed with a network error.
+    /// This is the last message.
+    Failed(Error),
+}
+
+/// Forwards the response body to the stream of `XMLHttpRequest::response_stream`,
+/// and disconnects it once the request ends.
+struct ChannelSink(RefCell<Option<Sender<ResponseStreamMsg>>>);
+
+impl ChannelSink {
+    fn send(&self, msg: ResponseStreamMsg) {
+        if let Some(ref sender) = *self.0.borrow() {
+            let _ = sender.send(msg);
+        }
+    }
+}
+
+impl ResponseSink for ChannelSink {
+    fn write(&self, _xhr: &XMLHttpRequest, chunk: &[u8]) {
+        self.send(ResponseStreamMsg::Chunk(chunk.to_vec()));
+    }
+
+    fn finish(&self, _xhr: &XMLHttpRequest) {
+        self.send(ResponseStreamMsg::Done);
+        self.0.borrow_mut().take();
+    }
+
+    fn fail(&self, _xhr: &XMLHttpRequest, error: &Error) {
+        self.send(ResponseStreamMsg::Failed(error.clone()));
+        self.0.borrow_mut().take();
+    }
+}
+
+/// Responses carrying an `ETag`, keyed on the URL passed to `open()`. XHRs
+/// sharing a cache revalidate a cached URL with `If-None-Match` on GET, and
+/// replay the cached response when the server answers 304 Not Modified.
+/// This is for embedders that manage revalidation themselves, the HTTP cache
+/// otherwise takes care of it.
+#[derive(Default, JSTraceable, MallocSizeOf)]
+pub struct EtagCache {
+    entries: DomRefCell<HashMap<ServoUrl, EtagCacheEntry>>,
+}
+
+impl EtagCache {
+    pub fn new() -> EtagCache {
+        EtagCache::default()
+    }
+
+    /// Forget all cached responses.
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+}
+
+#[derive(Clone, JSTraceable, MallocSizeOf)]
+struct EtagCacheEntry {
+    status: u16,
+    status_text: Vec<u8>,
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
 /// Closure of required data for each async network event that comprises the
 /// XHR's response.
 struct XHRContext {
@@ -144,6 +396,18 @@ pub struct XMLHttpRequest {
     response_json: Heap<JSVal>,
     #[ignore_malloc_size_of = "Defined in hyper"]
     response_headers: DomRefCell<HeaderMap>,
+    response_set_cookies: DomRefCell<Vec<Vec<u8>>>,
+    record_response_set_cookies: Cell<bool>,
+    response_from_cache: Cell<bool>,
+    cors_preflight_performed: Cell<bool>,
+    connection_reused: Cell<bool>,
+    tls_info: DomRefCell<Option<TlsInfo>>,
+    record_connection_details: Cell<bool>,
+    http_version: DomRefCell<Option<String>>,
+    redirect_count: Cell<u16>,
+    redirect_chain: DomRefCell<Vec<(ServoUrl, u16)>>,
+    record_redirect_chain: Cell<bool>,
+    network_error: DomRefCell<Option<NetworkError>>,
     #[ignore_malloc_size_of = "Defined in hyper"]
     override_mime_type: DomRefCell<Option<Mime>>,
     override_charset: DomRefCell<Option<&'static Encoding>>,
@@ -154,18 +418,93 @@ pub struct XMLHttpRequest {
     request_url: DomRefCell<Option<ServoUrl>>,
     #[ignore_malloc_size_of = "Defined in hyper"]
     request_headers: DomRefCell<HeaderMap>,
+    /// The headers of the last request as it was handed to the network.
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    sent_request_headers: DomRefCell<HeaderMap>,
+    record_sent_request_headers: Cell<bool>,
     request_body_len: Cell<usize>,
     sync: Cell<bool>,
     upload_complete: Cell<bool>,
     send_flag: Cell<bool>,
+    manual_redirect: Cell<bool>,
+    integrity_metadata: DomRefCell<String>,
+    user_agent: DomRefCell<Option<String>>,
+    compress_request_body: Cell<bool>,
+    auto_response_type: Cell<bool>,
+    keep_alive: Cell<bool>,
+    #[ignore_malloc_size_of = "trait objects are hard"]
+    ready_state_observers: DomRefCell<Vec<Box<dyn ReadyStateObserver>>>,
+    recorded_ready_states: DomRefCell<Vec<u16>>,
+    #[ignore_malloc_size_of = "trait objects are hard"]
+    completion_observers: DomRefCell<Vec<Box<dyn CompletionObserver>>>,
+    recorded_completions: DomRefCell<Vec<String>>,
+    #[ignore_malloc_size_of = "trait objects are hard"]
+    request_start_observers: DomRefCell<Vec<Box<dyn RequestStartObserver>>>,
+    recorded_request_starts: DomRefCell<Vec<String>>,
+    /// The headers of the last request start that was recorded.
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    recorded_request_headers: DomRefCell<HeaderMap>,
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    redacted_request_headers: DomRefCell<Vec<HeaderName>>,
+    #[ignore_malloc_size_of = "trait objects are hard"]
+    response_sinks: DomRefCell<Vec<Box<dyn ResponseSink>>>,
+    /// Whether a `ResponseTee` is among the response sinks.
+    teeing: Cell<bool>,
+    tee_buffer: DomRefCell<Vec<u8>>,
+    teed_response: DomRefCell<Option<Vec<u8>>>,
+    #[ignore_malloc_size_of = "channels are hard"]
+    response_stream: DomRefCell<Option<Receiver<ResponseStreamMsg>>>,
+    #[ignore_malloc_size_of = "trait objects are hard"]
+    url_rewriter: DomRefCell<Option<Box<dyn UrlRewriter>>>,
+    #[ignore_malloc_size_of = "Rc is hard"]
+    etag_cache: DomRefCell<Option<Rc<EtagCache>>>,
+    /// The cached response the current request revalidates, if any.
+    etag_revalidation: DomRefCell<Option<EtagCacheEntry>>,
+    /// The cached body to deliver once a revalidation got a 304.
+    etag_replay: DomRefCell<Option<Vec<u8>>>,
 
     timeout_cancel: DomRefCell<Option<OneshotTimerHandle>>,
+    #[ignore_malloc_size_of = "Defined in std"]
+    deadline: Cell<Option<Instant>>,
+    #[ignore_malloc_size_of = "Defined in std"]
+    headers_timeout: Cell<Option<Duration>>,
+    #[ignore_malloc_size_of = "Defined in std"]
+    body_idle_timeout: Cell<Option<Duration>>,
+    /// The timer of the headers or body idle timeout, whichever is running.
+    stall_timeout_cancel: DomRefCell<Option<OneshotTimerHandle>>,
+    /// Bytes per second of the response body delivered to script, if capped.
+    bandwidth_limit: Cell<Option<u64>>,
+    /// Received bytes of the body held back by the bandwidth limit.
+    throttled_body: DomRefCell<Vec<u8>>,
+    /// The response ended while part of its body was held back: whether it
+    /// was replayed from the ETag cache.
+    throttled_completion: Cell<Option<bool>>,
+    throttle_cancel: DomRefCell<Option<OneshotTimerHandle>>,
+    /// When the current request was sent, if its throughput is measured.
+    #[ignore_malloc_size_of = "Defined in std"]
+    send_time: Cell<Option<Instant>>,
+    /// Bytes of the response body received per second since the request was
+    /// sent, as of the last chunk.
+    throughput: Cell<f64>,
+    measure_throughput: Cell<bool>,
     fetch_time: Cell<i64>,
     generation_id: Cell<GenerationId>,
     response_status: Cell<Result<(), ()>>,
     referrer_url: Option<ServoUrl>,
     referrer_policy: Option<ReferrerPolicy>,
+    /// Replaces the document's referrer for subsequent requests, if set.
+    referrer_override: DomRefCell<Option<Referrer>>,
+    /// Cancels the ongoing fetch when dropped. An in-flight fetch holds a
+    /// `Trusted` reference to the XHR, so the XHR can't be collected while
+    /// the fetch may still deliver events.
     canceller: DomRefCell<FetchCanceller>,
+    /// Whether the global tracks this XHR, which it does while a request is
+    /// in flight so that it can be aborted along with the others.
+    tracked: Cell<bool>,
+    /// The XHR as cancel handles of the request in flight see it, until that
+    /// request finishes.
+    #[ignore_malloc_size_of = "Arc"]
+    cancel_target: DomRefCell<Option<CancelTarget>>,
 }
 
 impl XMLHttpRequest {
@@ -194,24 +533,75 @@ impl XMLHttpRequest {
             response_arraybuffer: Heap::default(),
             response_json: Heap::default(),
             response_headers: DomRefCell::new(HeaderMap::new()),
+            response_set_cookies: DomRefCell::new(vec![]),
+            record_response_set_cookies: Cell::new(false),
+            response_from_cache: Cell::new(false),
+            cors_preflight_performed: Cell::new(false),
+            connection_reused: Cell::new(false),
+            tls_info: DomRefCell::new(None),
+            record_connection_details: Cell::new(false),
+            http_version: DomRefCell::new(None),
+            redirect_count: Cell::new(0),
+            redirect_chain: DomRefCell::new(vec![]),
+            record_redirect_chain: Cell::new(false),
+            network_error: DomRefCell::new(None),
             override_mime_type: DomRefCell::new(None),
             override_charset: DomRefCell::new(None),
 
             request_method: DomRefCell::new(Method::GET),
             request_url: DomRefCell::new(None),
             request_headers: DomRefCell::new(HeaderMap::new()),
+            sent_request_headers: DomRefCell::new(HeaderMap::new()),
+            record_sent_request_headers: Cell::new(false),
             request_body_len: Cell::new(0),
             sync: Cell::new(false),
             upload_complete: Cell::new(false),
             send_flag: Cell::new(false),
+            manual_redirect: Cell::new(false),
+            integrity_metadata: DomRefCell::new(String::new()),
+            user_agent: DomRefCell::new(None),
+            compress_request_body: Cell::new(false),
+            auto_response_type: Cell::new(false),
+            keep_alive: Cell::new(false),
+            ready_state_observers: DomRefCell::new(vec![]),
+            recorded_ready_states: DomRefCell::new(vec![]),
+            completion_observers: DomRefCell::new(vec![]),
+            recorded_completions: DomRefCell::new(vec![]),
+            request_start_observers: DomRefCell::new(vec![]),
+            recorded_request_starts: DomRefCell::new(vec![]),
+            recorded_request_headers: DomRefCell::new(HeaderMap::new()),
+            redacted_request_headers: DomRefCell::new(vec![]),
+            response_sinks: DomRefCell::new(vec![]),
+            teeing: Cell::new(false),
+            tee_buffer: DomRefCell::new(vec![]),
+            teed_response: DomRefCell::new(None),
+            response_stream: DomRefCell::new(None),
+            url_rewriter: DomRefCell::new(None),
+            etag_cache: DomRefCell::new(None),
+            etag_revalidation: DomRefCell::new(None),
+            etag_replay: DomRefCell::new(None),
 
             timeout_cancel: DomRefCell::new(None),
+            deadline: Cell::new(None),
+            headers_timeout: Cell::new(None),
+            body_idle_timeout: Cell::new(None),
+            stall_timeout_cancel: DomRefCell::new(None),
+            bandwidth_limit: Cell::new(None),
+            throttled_body: DomRefCell::new(vec![]),
+            throttled_completion: Cell::new(None),
+            throttle_cancel: DomRefCell::new(None),
+            send_time: Cell::new(None),
+            throughput: Cell::new(0.0),
+            measure_throughput: Cell::new(false),
             fetch_time: Cell::new(0),
             generation_id: Cell::new(GenerationId(0)),
             response_status: Cell::new(Ok(())),
             referrer_url: referrer_url,
             referrer_policy: referrer_policy,
+            referrer_override: DomRefCell::new(None),
             canceller: DomRefCell::new(Default::default()),
+            tracked: Cell::new(false),
+            cancel_target: DomRefCell::new(None),
         }
     }
     pub fn new(global: &GlobalScope) -> DomRoot<XMLHttpRequest> {
@@ -227,6 +617,11 @@ impl XMLHttpRequest {
         Ok(XMLHttpRequest::new(global))
     }
 
+    // Servo-specific, for testing only.
+    pub fn AbortAll(global: &GlobalScope) -> bool {
+        global.abort_xhrs()
+    }
+
     fn sync_in_window(&self) -> bool {
         self.sync.get() && self.global().is::<Window>()
     }
@@ -249,7 +644,8 @@ impl XMLHttpRequest {
 
             fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>) {
                 let xhr = self.xhr.root();
-                let rv = xhr.process_headers_available(self.gen_id, metadata);
+                let rv =
+                    xhr.process_headers_available(self.gen_id, metadata, &mut self.resource_timing);
                 if rv.is_err() {
                     *self.sync_status.borrow_mut() = Some(rv);
                 }
@@ -263,10 +659,12 @@ impl XMLHttpRequest {
                 &mut self,
                 response: Result<ResourceFetchTiming, NetworkError>,
             ) {
-                let rv = self
-                    .xhr
-                    .root()
-                    .process_response_complete(self.gen_id, response.map(|_| ()));
+                let xhr = self.xhr.root();
+                // The same timing ends up in the XHR's resource timing entry.
+                if let Ok(ref timing) = response {
+                    xhr.redirect_count.set(timing.redirect_count);
+                }
+                let rv = xhr.process_response_complete(self.gen_id, response.map(|_| ()));
                 *self.sync_status.borrow_mut() = Some(rv);
             }
 
@@ -299,6 +697,7 @@ impl XMLHttpRequest {
             }
         }
 
+        let xhr = context.lock().unwrap().xhr.root();
         let (action_sender, action_receiver) = ipc::channel().unwrap();
 
         let listener = NetworkListener {
@@ -312,6 +711,22 @@ impl XMLHttpRequest {
                 listener.notify_fetch(message.to().unwrap());
             }),
         );
+        let observers = xhr.request_start_observers.borrow();
+        if !observers.is_empty() {
+            // Only the copy observers see is redacted, not the sent headers.
+            let mut headers = init.headers.clone();
+            for name in xhr.redacted_request_headers.borrow().iter() {
+                if headers.contains_key(name) {
+                    headers.insert(
+                        name.clone(),
+                        HeaderValue::from_static(REDACTED_HEADER_VALUE),
+                    );
+                }
+            }
+            for observer in observers.iter() {
+                observer.request_started(&xhr, &init.method, &init.url, &headers);
+            }
+        }
         global
             .core_resource_thread()
             .send(Fetch(
@@ -424,9 +839,11 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
                 *self.request_url.borrow_mut() = Some(parsed_url);
                 self.sync.set(!r#async);
                 *self.request_headers.borrow_mut() = HeaderMap::new();
+                *self.sent_request_headers.borrow_mut() = HeaderMap::new();
                 self.send_flag.set(false);
-                *self.status_text.borrow_mut() = ByteString::new(vec![]);
-                self.status.set(0);
+                // The override MIME type and charset are deliberately left alone:
+                // an override set before open() applies to the next request.
+                self.reset_response();
 
                 // Step 13
                 if self.ready_state.get() != XMLHttpRequestState::Opened {
@@ -488,10 +905,32 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             None => value.into(),
         };
 
-        headers.insert(
-            HeaderName::from_str(name_str).unwrap(),
-            HeaderValue::from_bytes(&value).unwrap(),
-        );
+        // Keep scripts from accumulating an unbounded amount of header data.
+        // Combining a value into an existing header doesn't add a name.
+        let max_headers = pref!(dom.xhr.max_request_headers);
+        if max_headers > 0 &&
+            !headers.contains_key(name_str) &&
+            headers.keys_len() as i64 >= max_headers
+        {
+            return Err(Error::Syntax);
+        }
+        let max_bytes = pref!(dom.xhr.max_request_header_bytes);
+        if max_bytes > 0 {
+            let size = |name: &str, value: &[u8]| name.len() + value.len();
+            let total: usize = headers
+                .iter()
+                .filter(|&(other, _)| other.as_str() != name_str)
+                .map(|(other, value)| size(other.as_str(), value.as_bytes()))
+                .sum();
+            if (total + size(name_str, &value)) as i64 > max_bytes {
+                return Err(Error::Syntax);
+            }
+        }
+
+        // Both parts were validated above, but don't take down the script
+        // thread should the header crate reject a combined value anyway.
+        let value = HeaderValue::from_bytes(&value).map_err(|_| Error::Syntax)?;
+        headers.insert(HeaderName::from_str(name_str).unwrap(), value);
         Ok(())
     }
 
@@ -510,16 +949,10 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         self.timeout.set(timeout);
 
         if self.send_flag.get() {
-            if timeout == 0 {
-                self.cancel_timeout();
-                return Ok(());
-            }
-            let progress = time::now().to_timespec().sec - self.fetch_time.get();
-            if timeout > (progress * 1000) as u32 {
-                self.set_timeout(timeout - (progress * 1000) as u32);
-            } else {
-                // Immediately execute the timeout steps
-                self.set_timeout(0);
+            self.cancel_timeout();
+            if let Some(duration_ms) = self.remaining_timeout() {
+                // A duration of 0 immediately executes the timeout steps
+                self.set_timeout(duration_ms);
             }
         }
         Ok(())
@@ -558,6 +991,17 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         if self.ready_state.get() != XMLHttpRequestState::Opened || self.send_flag.get() {
             return Err(Error::InvalidState);
         }
+        // The global may have been torn down since open(); don't start a
+        // fetch it can't receive.
+        if !self.global_is_active() {
+            return Err(Error::InvalidState);
+        }
+        // A successful open() always sets the request URL, but don't rely on
+        // that invariant to avoid panicking.
+        let request_url = match self.request_url.borrow().clone() {
+            Some(url) => url,
+            None => return Err(Error::InvalidState),
+        };
 
         // Step 3
         let data = match *self.request_method.borrow() {
@@ -588,9 +1032,28 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             None => None,
         };
 
+        // Servo-specific: the server must understand gzip-encoded request
+        // bodies, so this only happens when an embedder opted in.
+        let compressed = self.compress_request_body.get() &&
+            extracted_or_serialized
+                .as_ref()
+                .map_or(false, |e| !e.0.is_empty());
+        let extracted_or_serialized = if compressed {
+            extracted_or_serialized.map(|(bytes, content_type)| (gzip(&bytes), content_type))
+        } else {
+            extracted_or_serialized
+        };
+
         self.request_body_len
             .set(extracted_or_serialized.as_ref().map_or(0, |e| e.0.len()));
 
+        // Requests outliving their global may only carry a small body.
+        if self.keep_alive.get() && self.request_body_len.get() > KEEP_ALIVE_BODY_LIMIT {
+            return Err(Error::Type(
+                "keepalive request bodies are limited to 64 KiB".to_owned(),
+            ));
+        }
+
         // todo preserved headers?
 
         // Step 6
@@ -603,6 +1066,7 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         });
         // Step 8
         self.send_flag.set(true);
+        self.start_tracking();
 
         // Step 9
         if !self.sync.get() {
@@ -623,6 +1087,12 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             }
         }
 
+        // Servo-specific: an embedder may route the request elsewhere.
+        let request_url = match *self.url_rewriter.borrow() {
+            Some(ref rewriter) => rewriter.rewrite_url(&request_url).unwrap_or(request_url),
+            None => request_url,
+        };
+
         // Step 5
         //TODO - set referrer_policy/referrer_url in request
         let has_handlers = self.upload.upcast::<EventTarget>().has_handlers();
@@ -631,34 +1101,43 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
         } else {
             CredentialsMode::CredentialsSameOrigin
         };
-        let use_url_credentials = if let Some(ref url) = *self.request_url.borrow() {
-            !url.username().is_empty() || url.password().is_some()
-        } else {
-            unreachable!()
-        };
+        let use_url_credentials =
+            !request_url.username().is_empty() || request_url.password().is_some();
 
-        let mut request = RequestBuilder::new(self.request_url.borrow().clone().unwrap())
+        let mut request = RequestBuilder::new(request_url)
             .method(self.request_method.borrow().clone())
             .headers((*self.request_headers.borrow()).clone())
             .unsafe_request(true)
             // XXXManishearth figure out how to avoid this clone
             .body(extracted_or_serialized.as_ref().map(|e| e.0.clone()))
-            // XXXManishearth actually "subresource", but it doesn't exist
+            // XHR uses the empty destination, there is no "subresource" one.
             // https://github.com/whatwg/xhr/issues/71
             .destination(Destination::None)
             .synchronous(self.sync.get())
             .mode(RequestMode::CorsMode)
+            .redirect_mode(if self.manual_redirect.get() {
+                RedirectMode::Manual
+            } else {
+                RedirectMode::Follow
+            })
+            .integrity_metadata(self.integrity_metadata.borrow().clone())
+            .keep_alive(self.keep_alive.get())
+            .record_connection_details(self.records_connection_details())
+            .record_redirects(self.records_redirect_chain())
             .use_cors_preflight(has_handlers)
             .credentials_mode(credentials_mode)
             .use_url_credentials(use_url_credentials)
             .origin(self.global().origin().immutable().clone())
-            .referrer(
-                self.referrer_url
+            .referrer(match *self.referrer_override.borrow() {
+                Some(ref referrer) => Some(referrer.clone()),
+                None => self
+                    .referrer_url
                     .clone()
                     .map(|referrer_url| Referrer::ReferrerUrl(referrer_url)),
-            )
+            })
             .referrer_policy(self.referrer_policy.clone())
             .pipeline_id(Some(self.global().pipeline_id()));
+        request.csp_list = self.global().get_csp_list();
 
         // step 4 (second half)
         match extracted_or_serialized {
@@ -722,7 +1201,68 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             _ => (),
         }
 
+        // Browsers send `Accept: */*` for XHR unless the author set their own;
+        // don't rely on the fetch layer's destination-based default for this.
+        if !request.headers.contains_key(header::ACCEPT) {
+            request
+                .headers
+                .insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        }
+
+        // `User-Agent` is a forbidden header name, so only embedders get here.
+        if let Some(ref user_agent) = *self.user_agent.borrow() {
+            request.headers.insert(
+                header::USER_AGENT,
+                HeaderValue::from_str(user_agent).unwrap(),
+            );
+        }
+
+        if compressed {
+            request
+                .headers
+                .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        }
+
+        // Servo-specific: revalidate a response the embedder's ETag cache
+        // holds, unless the author made the request conditional already.
+        *self.etag_revalidation.borrow_mut() = None;
+        *self.etag_replay.borrow_mut() = None;
+        if let Some(ref cache) = *self.etag_cache.borrow() {
+            let cached = self
+                .request_url
+                .borrow()
+                .as_ref()
+                .and_then(|url| cache.entries.borrow().get(url).cloned());
+            if let Some(entry) = cached {
+                if request.method == Method::GET &&
+                    !request.headers.contains_key(header::IF_NONE_MATCH)
+                {
+                    if let Some(etag) = entry.headers.get(header::ETAG).cloned() {
+                        request.headers.insert(header::IF_NONE_MATCH, etag);
+                        *self.etag_revalidation.borrow_mut() = Some(entry);
+                    }
+                }
+            }
+        }
+
+        // https://privacycg.github.io/gpc-spec/#the-sec-gpc-header-field-for-http-requests
+        // `Sec-` is a forbidden header prefix, so this can't clash with an author value.
+        if pref!(dom.xhr.global_privacy_control.enabled) {
+            request.headers.insert(
+                HeaderName::from_static("sec-gpc"),
+                HeaderValue::from_static("1"),
+            );
+        }
+
         self.fetch_time.set(time::now().to_timespec().sec);
+        // Only read the clock for embedders that look at the throughput.
+        self.send_time.set(
+            if self.measure_throughput.get() || pref!(dom.xhr.testing.enabled) {
+                Some(Instant::now())
+            } else {
+                None
+            },
+        );
 
         let rv = self.fetch(request, &self.global());
         // Step 10
@@ -730,10 +1270,10 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             return rv;
         }
 
-        let timeout = self.timeout.get();
-        if timeout > 0 {
-            self.set_timeout(timeout);
+        if let Some(duration_ms) = self.remaining_timeout() {
+            self.set_timeout(duration_ms);
         }
+        self.set_stall_timeout(self.headers_timeout.get());
         Ok(())
     }
 
@@ -755,8 +1295,12 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
                 return;
             }
         }
-        // Step 3
-        self.ready_state.set(XMLHttpRequestState::Unsent);
+        // Step 3, without a readystatechange event. A request that was only
+        // opened stays opened.
+        if self.ready_state.get() == XMLHttpRequestState::Done {
+            self.set_ready_state(XMLHttpRequestState::Unsent);
+            self.reset_response();
+        }
     }
 
     // https://xhr.spec.whatwg.org/#the-responseurl-attribute
@@ -810,8 +1354,11 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             for value in values {
                 if !first {
                     vec.extend(", ".as_bytes());
-                    first = false;
                 }
+                first = false;
+                // A `HeaderValue` can't contain CR or LF, so this can't
+                // inject lines. Folded values are rejected by the HTTP parser
+                // rather than unfolded, making the response a network error.
                 vec.extend(value.as_bytes());
             }
             vec.extend("\r\n".as_bytes());
@@ -830,22 +1377,32 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             },
             _ => {},
         }
-        // Step 2
-        let override_mime = mime.parse::<Mime>().map_err(|_| Error::Syntax)?;
-        // Step 3
+        // Step 2, 3: an unparseable MIME type, including the empty string,
+        // doesn't throw but overrides with application/octet-stream.
+        let override_mime = match mime.parse::<Mime>() {
+            Ok(mime) => mime,
+            Err(_) => {
+                *self.override_mime_type.borrow_mut() = Some(mime::APPLICATION_OCTET_STREAM);
+                *self.override_charset.borrow_mut() = None;
+                return Ok(());
+            },
+        };
         let mime_str = override_mime.as_ref();
         let mime_parts: Vec<&str> = mime_str.split(";").collect();
         let mime_no_params = if mime_parts.len() > 1 {
-            mime_parts[0].parse().unwrap()
+            // Reparsing the part before the parameters shouldn't fail, but
+            // don't panic on whatever the parser let through.
+            mime_parts[0]
+                .trim()
+                .parse()
+                .unwrap_or(mime::APPLICATION_OCTET_STREAM)
         } else {
             override_mime.clone()
         };
 
         *self.override_mime_type.borrow_mut() = Some(mime_no_params);
         // Step 4
-        let value = override_mime.get_param(mime::CHARSET);
-        *self.override_charset.borrow_mut() =
-            value.and_then(|value| Encoding::for_label(value.as_ref().as_bytes()));
+        *self.override_charset.borrow_mut() = charset_encoding(&override_mime);
         Ok(())
     }
 
@@ -880,16 +1437,16 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
 
     #[allow(unsafe_code)]
     // https://xhr.spec.whatwg.org/#the-response-attribute
-    fn Response(&self, cx: JSContext) -> JSVal {
+    fn Response(&self, cx: JSContext) -> Fallible<JSVal> {
         rooted!(in(*cx) let mut rval = UndefinedValue());
-        match self.response_type.get() {
+        match self.effective_response_type() {
             XMLHttpRequestResponseType::_empty | XMLHttpRequestResponseType::Text => unsafe {
                 let ready_state = self.ready_state.get();
                 // Step 2
                 if ready_state == XMLHttpRequestState::Done ||
                     ready_state == XMLHttpRequestState::Loading
                 {
-                    self.text_response().to_jsval(*cx, rval.handle_mut());
+                    self.text_response()?.to_jsval(*cx, rval.handle_mut());
                 } else {
                     // Step 1
                     "".to_jsval(*cx, rval.handle_mut());
@@ -897,7 +1454,7 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             },
             // Step 1
             _ if self.ready_state.get() != XMLHttpRequestState::Done => {
-                return NullValue();
+                return Ok(NullValue());
             },
             // Step 2
             XMLHttpRequestResponseType::Document => unsafe {
@@ -911,10 +1468,10 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             },
             XMLHttpRequestResponseType::Arraybuffer => match self.arraybuffer_response(cx) {
                 Some(js_object) => unsafe { js_object.to_jsval(*cx, rval.handle_mut()) },
-                None => return NullValue(),
+                None => return Ok(NullValue()),
             },
         }
-        rval.get()
+        Ok(rval.get())
     }
 
     // https://xhr.spec.whatwg.org/#the-responsetext-attribute
@@ -924,7 +1481,7 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
                 Ok(USVString(String::from(match self.ready_state.get() {
                     // Step 3
                     XMLHttpRequestState::Loading | XMLHttpRequestState::Done => {
-                        self.text_response()
+                        self.text_response()?
                     },
                     // Step 2
                     _ => "".to_owned(),
@@ -941,6 +1498,14 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             XMLHttpRequestResponseType::_empty | XMLHttpRequestResponseType::Document => {
                 // Step 3
                 if let XMLHttpRequestState::Done = self.ready_state.get() {
+                    // Step 5 of getting a document response, which the
+                    // automatic response type only lifts for `response`.
+                    let is_html = self.final_mime_type().map_or(false, |mime| {
+                        mime.type_() == mime::TEXT && mime.subtype() == mime::HTML
+                    });
+                    if is_html && self.response_type.get() == XMLHttpRequestResponseType::_empty {
+                        return Ok(None);
+                    }
                     Ok(self.document_response())
                 } else {
                     // Step 2
@@ -951,119 +1516,632 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             _ => Err(Error::InvalidState),
         }
     }
-}
 
-pub type TrustedXHRAddress = Trusted<XMLHttpRequest>;
+    // Servo-specific, for testing only.
+    fn GenerationId(&self) -> u32 {
+        self.generation_id().0
+    }
 
-impl XMLHttpRequest {
-    fn change_ready_state(&self, rs: XMLHttpRequestState) {
-        assert_ne!(self.ready_state.get(), rs);
-        self.ready_state.set(rs);
-        let event = Event::new(
-            &self.global(),
-            atom!("readystatechange"),
-            EventBubbles::DoesNotBubble,
-            EventCancelable::Cancelable,
-        );
-        event.fire(self.upcast());
+    // Servo-specific, for testing only.
+    fn ResponseFromCache(&self) -> bool {
+        self.response_from_cache()
     }
 
-    fn process_headers_available(
-        &self,
-        gen_id: GenerationId,
-        metadata: Result<FetchMetadata, NetworkError>,
-    ) -> Result<(), Error> {
-        let metadata = match metadata {
-            Ok(meta) => match meta {
-                FetchMetadata::Unfiltered(m) => m,
-                FetchMetadata::Filtered { filtered, .. } => match filtered {
-                    FilteredMetadata::Basic(m) => m,
-                    FilteredMetadata::Cors(m) => m,
-                    FilteredMetadata::Opaque => return Err(Error::Network),
-                    FilteredMetadata::OpaqueRedirect => return Err(Error::Network),
-                },
-            },
-            Err(_) => {
-                self.process_partial_response(XHRProgress::Errored(gen_id, Error::Network));
-                return Err(Error::Network);
-            },
-        };
+    // Servo-specific, for testing only.
+    fn RecordReadyStates(&self) {
+        self.add_ready_state_observer(Box::new(ReadyStateRecorder));
+    }
+
+    // Servo-specific, for testing only.
+    fn RecordedReadyStates(&self) -> Vec<u16> {
+        self.recorded_ready_states.borrow().clone()
+    }
 
-        *self.response_url.borrow_mut() = metadata.final_url[..Position::AfterQuery].to_owned();
+    // Servo-specific, for testing only.
+    fn RecordCompletions(&self) {
+        self.add_completion_observer(Box::new(CompletionRecorder));
+    }
 
-        // XXXManishearth Clear cache entries in case of a network error
-        self.process_partial_response(XHRProgress::HeadersReceived(
-            gen_id,
-            metadata.headers.map(Serde::into_inner),
-            metadata.status,
-        ));
+    // Servo-specific, for testing only.
+    fn RecordedCompletions(&self) -> Vec<DOMString> {
+        self.recorded_completions
+            .borrow()
+            .iter()
+            .map(|record| DOMString::from(record.clone()))
+            .collect()
+    }
+
+    // Servo-specific, for testing only.
+    fn RecordRequestStarts(&self) {
+        self.add_request_start_observer(Box::new(RequestStartRecorder));
+    }
+
+    // Servo-specific, for testing only.
+    fn RecordedRequestStarts(&self) -> Vec<DOMString> {
+        self.recorded_request_starts
+            .borrow()
+            .iter()
+            .map(|record| DOMString::from(record.clone()))
+            .collect()
+    }
+
+    // Servo-specific, for testing only.
+    fn RecordedRequestHeader(&self, name: ByteString) -> Option<ByteString> {
+        let name = HeaderName::from_bytes(&name).ok()?;
+        self.recorded_request_headers
+            .borrow()
+            .get(&name)
+            .map(|value| ByteString::new(value.as_bytes().to_vec()))
+    }
+
+    // Servo-specific, for testing only.
+    fn SentRequestHeader(&self, name: ByteString) -> Option<ByteString> {
+        let name = HeaderName::from_bytes(&name).ok()?;
+        self.sent_request_headers()
+            .get(&name)
+            .map(|value| ByteString::new(value.as_bytes().to_vec()))
+    }
+
+    // Servo-specific, for testing only.
+    fn RedactRequestHeaders(&self, names: Vec<ByteString>) -> ErrorResult {
+        let names = names
+            .iter()
+            .map(|name| HeaderName::from_bytes(name).map_err(|_| Error::Syntax))
+            .collect::<Result<_, _>>()?;
+        self.set_redacted_request_headers(names);
         Ok(())
     }
 
-    fn process_data_available(&self, gen_id: GenerationId, payload: Vec<u8>) {
-        self.process_partial_response(XHRProgress::Loading(gen_id, payload));
+    // Servo-specific, for testing only.
+    fn UseEtagCache(&self) {
+        self.set_etag_cache(Some(Rc::new(EtagCache::new())));
     }
 
-    fn process_response_complete(
-        &self,
-        gen_id: GenerationId,
-        status: Result<(), NetworkError>,
-    ) -> ErrorResult {
-        match status {
-            Ok(()) => {
-                self.process_partial_response(XHRProgress::Done(gen_id));
-                Ok(())
-            },
-            Err(_) => {
-                self.process_partial_response(XHRProgress::Errored(gen_id, Error::Network));
-                Err(Error::Network)
-            },
-        }
+    // Servo-specific, for testing only.
+    fn RewriteUrls(&self, from: USVString, to: USVString) {
+        self.set_url_rewriter(Some(Box::new(PrefixRewriter {
+            from: from.0,
+            to: to.0,
+        })));
     }
 
-    fn process_partial_response(&self, progress: XHRProgress) {
-        let msg_id = progress.generation_id();
+    // Servo-specific, for testing only.
+    fn SimulateResponseChunk(&self, chunk: ByteString) {
+        self.process_data_available(self.generation_id.get(), chunk.into());
+    }
 
-        // Aborts processing if abort() or open() was called
-        // (including from one of the event handlers called below)
-        macro_rules! return_if_fetch_was_terminated(
-            () => (
-                if msg_id != self.generation_id.get() {
-                    return
-                }
-            );
-        );
+    // Servo-specific, for testing only.
+    fn SimulateStatuslessResponse(&self, body: ByteString) {
+        // Replace the ongoing fetch with a response as an interception could
+        // synthesize it, without a status line or headers.
+        self.terminate_ongoing_fetch();
+        let gen_id = self.generation_id.get();
+        self.process_partial_response(XHRProgress::HeadersReceived(gen_id, None, None));
+        self.process_data_available(gen_id, body.into());
+        let _ = self.process_response_complete(gen_id, Ok(()));
+    }
 
-        // Ignore message if it belongs to a terminated fetch
-        return_if_fetch_was_terminated!();
+    // Servo-specific, for testing only.
+    fn TeeResponse(&self) {
+        if !self.teeing.replace(true) {
+            self.add_response_sink(Box::new(ResponseTee));
+        }
+    }
 
-        // Ignore messages coming from previously-errored responses or requests that have timed out
-        if self.response_status.get().is_err() {
-            return;
+    // Servo-specific, for testing only.
+    fn TeedResponse(&self) -> Option<ByteString> {
+        self.teed_response.borrow().clone().map(ByteString::new)
+    }
+
+    // Servo-specific, for testing only.
+    fn StreamResponse(&self) {
+        if self.response_stream.borrow().is_none() {
+            *self.response_stream.borrow_mut() = Some(self.response_stream());
         }
+    }
 
-        match progress {
-            XHRProgress::HeadersReceived(_, headers, status) => {
-                assert!(self.ready_state.get() == XMLHttpRequestState::Opened);
-                // For synchronous requests, this should not fire any events, and just store data
-                // XXXManishearth Find a way to track partial progress of the send (onprogresss for XHRUpload)
+    // Servo-specific, for testing only.
+    fn StreamedResponse(&self) -> Option<ByteString> {
+        let receiver = self.response_stream.borrow_mut().take()?;
+        let mut body = vec![];
+        for msg in receiver.try_iter() {
+            match msg {
+                ResponseStreamMsg::Chunk(chunk) => body.extend_from_slice(&chunk),
+                ResponseStreamMsg::Done => return Some(ByteString::new(body)),
+                ResponseStreamMsg::Failed(_) => return None,
+            }
+        }
+        None
+    }
 
-                // Part of step 13, send() (processing request end of file)
-                // Substep 1
-                self.upload_complete.set(true);
-                // Substeps 2-4
-                if !self.sync.get() {
-                    self.dispatch_upload_progress_event(atom!("progress"), None);
-                    return_if_fetch_was_terminated!();
-                    self.dispatch_upload_progress_event(atom!("load"), None);
-                    return_if_fetch_was_terminated!();
-                    self.dispatch_upload_progress_event(atom!("loadend"), None);
-                    return_if_fetch_was_terminated!();
-                }
-                // Part of step 13, send() (processing response)
-                // XXXManishearth handle errors, if any (substep 1)
-                // Substep 2
-                status.map(|(code, reason)| {
+    // Servo-specific, for testing only.
+    fn EmbedderSetCookies(&self) -> Vec<ByteString> {
+        self.response_set_cookies()
+            .into_iter()
+            .map(ByteString::new)
+            .collect()
+    }
+
+    // Servo-specific, for testing only.
+    fn Throughput(&self) -> f64 {
+        self.throughput()
+    }
+
+    // Servo-specific, for testing only.
+    fn BufferedLength(&self) -> u32 {
+        self.buffered_len() as u32
+    }
+
+    // Servo-specific, for testing only.
+    fn ResponseSlice(&self, start: u32, end: u32) -> ByteString {
+        ByteString::new(self.response_slice(start as usize, end as usize))
+    }
+
+    // Servo-specific, for testing only.
+    fn EmbedderResponseText(&self) -> Fallible<DOMString> {
+        self.response_text().map(DOMString::from)
+    }
+
+    // Servo-specific, for testing only.
+    fn CorsPreflightPerformed(&self) -> bool {
+        self.cors_preflight_performed()
+    }
+
+    // Servo-specific, for testing only.
+    fn ConnectionReused(&self) -> bool {
+        self.connection_reused()
+    }
+
+    // Servo-specific, for testing only.
+    fn GetTlsProtocol(&self) -> Option<DOMString> {
+        self.tls_info().map(|info| DOMString::from(info.protocol))
+    }
+
+    // Servo-specific, for testing only.
+    fn RedirectCount(&self) -> u16 {
+        self.redirect_count()
+    }
+
+    // Servo-specific, for testing only.
+    fn RedirectChain(&self) -> Vec<DOMString> {
+        self.redirect_chain()
+            .iter()
+            .map(|&(ref url, status)| DOMString::from(format!("{} {}", status, url)))
+            .collect()
+    }
+
+    // Servo-specific, for testing only.
+    fn GetStatusLine(&self) -> Option<ByteString> {
+        self.status_line().map(ByteString::new)
+    }
+
+    // Servo-specific, for testing only.
+    fn ManualRedirect(&self) -> bool {
+        self.manual_redirect.get()
+    }
+
+    // Servo-specific, for testing only.
+    fn SetManualRedirect(&self, manual: bool) {
+        self.set_manual_redirect(manual)
+    }
+
+    // Servo-specific, for testing only.
+    fn Integrity(&self) -> DOMString {
+        DOMString::from(self.integrity_metadata.borrow().clone())
+    }
+
+    // Servo-specific, for testing only.
+    fn SetIntegrity(&self, integrity: DOMString) {
+        self.set_integrity_metadata(integrity.into())
+    }
+
+    // Servo-specific, for testing only.
+    fn GetUserAgent(&self) -> Option<ByteString> {
+        self.user_agent
+            .borrow()
+            .clone()
+            .map(|user_agent| ByteString::new(user_agent.into_bytes()))
+    }
+
+    // Servo-specific, for testing only.
+    fn SetUserAgent(&self, user_agent: Option<ByteString>) -> ErrorResult {
+        let user_agent = match user_agent {
+            Some(user_agent) => Some(user_agent.as_str().ok_or(Error::Syntax)?.to_owned()),
+            None => None,
+        };
+        self.set_user_agent(user_agent)
+    }
+
+    // Servo-specific, for testing only.
+    // Mirrors the `referrer` member of `RequestInit`.
+    fn SetReferrer(&self, referrer: USVString) -> ErrorResult {
+        let referrer = if referrer.0.is_empty() {
+            Some(Referrer::NoReferrer)
+        } else {
+            let parsed = self
+                .global()
+                .api_base_url()
+                .join(&referrer.0)
+                .map_err(|_| Error::Type("Invalid referrer URL".to_owned()))?;
+            // A cross-origin referrer falls back to the document's.
+            if parsed.as_str() == "about:client" ||
+                !parsed.origin().same_origin(self.global().origin())
+            {
+                None
+            } else {
+                Some(Referrer::ReferrerUrl(parsed))
+            }
+        };
+        self.set_referrer_override(referrer);
+        Ok(())
+    }
+
+    // Servo-specific, for testing only.
+    fn AutoResponseType(&self) -> bool {
+        self.auto_response_type.get()
+    }
+
+    // Servo-specific, for testing only.
+    fn SetAutoResponseType(&self, auto: bool) {
+        self.set_auto_response_type(auto)
+    }
+
+    // Servo-specific, for testing only.
+    fn CompressRequestBody(&self) -> bool {
+        self.compress_request_body.get()
+    }
+
+    // Servo-specific, for testing only.
+    fn SetCompressRequestBody(&self, compress: bool) {
+        self.set_compress_request_body(compress)
+    }
+
+    // Servo-specific, for testing only.
+    fn Keepalive(&self) -> bool {
+        self.keep_alive.get()
+    }
+
+    // Servo-specific, for testing only.
+    fn SetKeepalive(&self, keep_alive: bool) {
+        self.set_keep_alive(keep_alive)
+    }
+
+    // Servo-specific, for testing only.
+    fn SetBandwidthLimit(&self, bytes_per_second: u32) {
+        self.set_bandwidth_limit(match bytes_per_second {
+            0 => None,
+            limit => Some(limit as u64),
+        });
+    }
+
+    // Servo-specific, for testing only.
+    fn CancelFromAnotherThread(&self) {
+        let handle = self.cancel_handle();
+        thread::spawn(move || handle.cancel());
+    }
+
+    // Servo-specific, for testing only.
+    fn SetHeadersTimeout(&self, milliseconds: u32) {
+        self.set_headers_timeout(match milliseconds {
+            0 => None,
+            ms => Some(Duration::from_millis(ms as u64)),
+        });
+    }
+
+    // Servo-specific, for testing only.
+    fn SetBodyIdleTimeout(&self, milliseconds: u32) {
+        self.set_body_idle_timeout(match milliseconds {
+            0 => None,
+            ms => Some(Duration::from_millis(ms as u64)),
+        });
+    }
+
+    // Servo-specific, for testing only.
+    fn SetDeadline(&self, milliseconds_from_now: i32) {
+        let offset = Duration::from_millis(milliseconds_from_now.abs() as u64);
+        let now = Instant::now();
+        let deadline = if milliseconds_from_now < 0 {
+            now.checked_sub(offset).unwrap_or(now)
+        } else {
+            now + offset
+        };
+        self.set_deadline(Some(deadline))
+    }
+
+    // Servo-specific, for testing only.
+    fn GetNetworkErrorReason(&self) -> Option<DOMString> {
+        self.last_network_error_reason().map(DOMString::from)
+    }
+
+    // Servo-specific, for testing only.
+    fn BlockedByCSP(&self) -> bool {
+        self.blocked_by_csp()
+    }
+}
+
+pub type TrustedXHRAddress = Trusted<XMLHttpRequest>;
+
+impl XMLHttpRequest {
+    fn set_ready_state(&self, rs: XMLHttpRequestState) {
+        if self.ready_state.get() == rs {
+            return;
+        }
+        self.ready_state.set(rs);
+        for observer in self.ready_state_observers.borrow().iter() {
+            observer.ready_state_changed(self, rs as u16);
+        }
+    }
+
+    fn change_ready_state(&self, rs: XMLHttpRequestState) {
+        assert_ne!(self.ready_state.get(), rs);
+        self.set_ready_state(rs);
+        let event = Event::new(
+            &self.global(),
+            atom!("readystatechange"),
+            EventBubbles::DoesNotBubble,
+            EventCancelable::Cancelable,
+        );
+        event.fire(self.upcast());
+    }
+
+    fn process_headers_available(
+        &self,
+        gen_id: GenerationId,
+        metadata: Result<FetchMetadata, NetworkError>,
+        resource_timing: &mut ResourceFetchTiming,
+    ) -> Result<(), Error> {
+        // Filtered responses don't carry `Set-Cookie` headers, but embedders
+        // may read them from the internal response.
+        let record_set_cookies =
+            self.record_response_set_cookies.get() || pref!(dom.xhr.testing.enabled);
+        let set_cookies = |metadata: &Metadata| -> Vec<Vec<u8>> {
+            if !record_set_cookies {
+                return vec![];
+            }
+            metadata.headers.as_ref().map_or(vec![], |headers| {
+                headers
+                    .get_all(header::SET_COOKIE)
+                    .iter()
+                    .map(|value| value.as_bytes().to_vec())
+                    .collect()
+            })
+        };
+        let metadata = match metadata {
+            Ok(meta) => match meta {
+                FetchMetadata::Unfiltered(m) => {
+                    *self.response_set_cookies.borrow_mut() = set_cookies(&m);
+                    m
+                },
+                FetchMetadata::Filtered { filtered, unsafe_ } => {
+                    *self.response_set_cookies.borrow_mut() = set_cookies(&unsafe_);
+                    match filtered {
+                        FilteredMetadata::Basic(m) => m,
+                        FilteredMetadata::Cors(m) => m,
+                        // In manual redirect mode, expose the redirect itself.
+                        FilteredMetadata::OpaqueRedirect if self.manual_redirect.get() => unsafe_,
+                        FilteredMetadata::Opaque | FilteredMetadata::OpaqueRedirect => {
+                            self.process_partial_response(XHRProgress::Errored(
+                                gen_id,
+                                Error::Network,
+                            ));
+                            return Err(Error::Network);
+                        },
+                    }
+                },
+            },
+            Err(error) => {
+                *self.network_error.borrow_mut() = Some(error);
+                self.process_partial_response(XHRProgress::Errored(gen_id, Error::Network));
+                return Err(Error::Network);
+            },
+        };
+
+        // Don't expose credentials the final URL may carry, e.g. after a
+        // redirect to a URL with userinfo.
+        let mut final_url = metadata.final_url.clone();
+        let _ = final_url.set_username("");
+        let _ = final_url.set_password(None);
+        *self.response_url.borrow_mut() = final_url[..Position::AfterQuery].to_owned();
+        self.response_from_cache.set(match metadata.cache_state {
+            CacheState::None => false,
+            CacheState::Local | CacheState::Validated | CacheState::Partial => true,
+        });
+        self.cors_preflight_performed
+            .set(metadata.cors_preflight_performed);
+        self.connection_reused.set(metadata.connection_reused);
+        if self.records_connection_details() {
+            *self.tls_info.borrow_mut() = metadata.tls_info.clone();
+            *self.http_version.borrow_mut() = metadata.http_version.clone();
+        }
+        if self.records_redirect_chain() {
+            let mut redirect_chain = metadata.redirects.clone();
+            redirect_chain.push((
+                metadata.final_url.clone(),
+                metadata.status.as_ref().map_or(0, |&(code, _)| code),
+            ));
+            *self.redirect_chain.borrow_mut() = redirect_chain;
+        }
+
+        // https://w3c.github.io/server-timing/#process-server-timing-header
+        if let Some(ref headers) = metadata.headers {
+            resource_timing.server_timing = parse_server_timing(headers);
+        }
+
+        // Local files have no status line; report them like other browsers
+        // do, with a status of 0 and an empty status text.
+        let mut status = if metadata.final_url.scheme() == "file" {
+            None
+        } else {
+            metadata.status
+        };
+        let mut headers = metadata.headers.map(Serde::into_inner);
+
+        // Servo-specific: a 304 to a revalidation means the cached response is
+        // still current, updated with the headers of the 304.
+        let revalidated = self.etag_revalidation.borrow_mut().take();
+        if let Some(entry) = revalidated {
+            if status.as_ref().map_or(false, |&(code, _)| code == 304) {
+                let mut cached_headers = entry.headers;
+                if let Some(mut fresh) = headers.take() {
+                    fresh.remove(header::CONTENT_LENGTH);
+                    cached_headers.extend(fresh);
+                }
+                headers = Some(cached_headers);
+                status = Some((entry.status, entry.status_text));
+                *self.etag_replay.borrow_mut() = Some(entry.body);
+            }
+        }
+
+        // XXXManishearth Clear cache entries in case of a network error
+        self.process_partial_response(XHRProgress::HeadersReceived(gen_id, headers, status));
+        Ok(())
+    }
+
+    fn process_data_available(&self, gen_id: GenerationId, payload: Vec<u8>) {
+        // Once some of the body is held back, the rest must queue up behind it
+        // even if the limit was lifted.
+        let throttled =
+            self.bandwidth_limit.get().is_some() || self.throttle_cancel.borrow().is_some();
+        if throttled && !self.sync.get() {
+            if gen_id != self.generation_id.get() {
+                return;
+            }
+            self.throttled_body.borrow_mut().extend_from_slice(&payload);
+            if self.throttle_cancel.borrow().is_none() {
+                self.schedule_throttled_release();
+            }
+            return;
+        }
+        self.process_partial_response(XHRProgress::Loading(gen_id, payload));
+    }
+
+    fn process_response_complete(
+        &self,
+        gen_id: GenerationId,
+        status: Result<(), NetworkError>,
+    ) -> ErrorResult {
+        match status {
+            Ok(()) => {
+                let replayed = self.etag_replay.borrow_mut().take();
+                let is_replay = replayed.is_some();
+                if let Some(body) = replayed {
+                    self.process_data_available(gen_id, body);
+                }
+                if !self.throttled_body.borrow().is_empty() {
+                    // Complete once the rest of the body was released.
+                    self.throttled_completion.set(Some(is_replay));
+                    return Ok(());
+                }
+                self.complete_response(gen_id, is_replay);
+                Ok(())
+            },
+            Err(error) => {
+                *self.network_error.borrow_mut() = Some(error);
+                self.process_partial_response(XHRProgress::Errored(gen_id, Error::Network));
+                Err(Error::Network)
+            },
+        }
+    }
+
+    fn complete_response(&self, gen_id: GenerationId, is_replay: bool) {
+        if !is_replay {
+            self.cache_etag_response(gen_id);
+        }
+        self.process_partial_response(XHRProgress::Done(gen_id));
+    }
+
+    fn schedule_throttled_release(&self) {
+        let callback = OneshotTimerCallback::XhrThrottle(XHRThrottleCallback {
+            xhr: Trusted::new(self),
+            generation_id: self.generation_id.get(),
+        });
+        let duration = Length::new(THROTTLE_INTERVAL_MS);
+        *self.throttle_cancel.borrow_mut() =
+            Some(self.global().schedule_callback(callback, duration));
+    }
+
+    /// Deliver the part of the held back body the bandwidth limit allows for
+    /// one interval, and complete the response once none is left.
+    fn release_throttled_body(&self, gen_id: GenerationId) {
+        self.throttle_cancel.borrow_mut().take();
+        if gen_id != self.generation_id.get() {
+            return;
+        }
+        // Lifting the limit releases everything at once.
+        let quota = match self.bandwidth_limit.get() {
+            Some(limit) => cmp::max(limit * THROTTLE_INTERVAL_MS / 1000, 1) as usize,
+            None => usize::max_value(),
+        };
+        let chunk: Vec<u8> = {
+            let mut body = self.throttled_body.borrow_mut();
+            let len = cmp::min(quota, body.len());
+            body.drain(..len).collect()
+        };
+        self.process_partial_response(XHRProgress::Loading(gen_id, chunk));
+        if gen_id != self.generation_id.get() {
+            return;
+        }
+        if !self.throttled_body.borrow().is_empty() {
+            self.schedule_throttled_release();
+        } else if let Some(is_replay) = self.throttled_completion.take() {
+            self.complete_response(gen_id, is_replay);
+        }
+    }
+
+    fn clear_throttled_body(&self) {
+        if let Some(handle) = self.throttle_cancel.borrow_mut().take() {
+            self.global().unschedule_callback(handle);
+        }
+        self.throttled_body.borrow_mut().clear();
+        self.throttled_completion.set(None);
+    }
+
+    fn process_partial_response(&self, progress: XHRProgress) {
+        let msg_id = progress.generation_id();
+
+        // Aborts processing if abort() or open() was called
+        // (including from one of the event handlers called below)
+        macro_rules! return_if_fetch_was_terminated(
+            () => (
+                if msg_id != self.generation_id.get() {
+                    return
+                }
+            );
+        );
+
+        // Ignore message if it belongs to a terminated fetch
+        return_if_fetch_was_terminated!();
+
+        // Ignore messages coming from previously-errored responses or requests that have timed out
+        if self.response_status.get().is_err() {
+            return;
+        }
+
+        match progress {
+            XHRProgress::HeadersReceived(_, headers, status) => {
+                assert!(self.ready_state.get() == XMLHttpRequestState::Opened);
+                // For synchronous requests, this should not fire any events, and just store data
+                // XXXManishearth Find a way to track partial progress of the send (onprogresss for XHRUpload)
+
+                // Part of step 13, send() (processing request end of file)
+                // Substep 1
+                // Without a request body, the upload was complete from the
+                // start and there are no upload events to fire.
+                let had_body = !self.upload_complete.get();
+                self.upload_complete.set(true);
+                // Substeps 2-4
+                if !self.sync.get() && had_body {
+                    self.dispatch_upload_progress_event(atom!("progress"), None);
+                    return_if_fetch_was_terminated!();
+                    self.dispatch_upload_progress_event(atom!("load"), None);
+                    return_if_fetch_was_terminated!();
+                    self.dispatch_upload_progress_event(atom!("loadend"), None);
+                    return_if_fetch_was_terminated!();
+                }
+                // Part of step 13, send() (processing response)
+                // XXXManishearth handle errors, if any (substep 1)
+                // Substep 2
+                status.map(|(code, reason)| {
                     self.status.set(code);
                     *self.status_text.borrow_mut() = ByteString::new(reason);
                 });
@@ -1071,7 +2149,7 @@ impl XMLHttpRequest {
                     .as_ref()
                     .map(|h| *self.response_headers.borrow_mut() = h.clone());
                 {
-                    let len = headers.and_then(|h| h.typed_get::<ContentLength>());
+                    let len = self.response_content_length();
                     let mut response = self.response.borrow_mut();
                     response.clear();
                     if let Some(len) = len {
@@ -1081,7 +2159,7 @@ impl XMLHttpRequest {
                         //
                         // this number is arbitrary, it's basically big enough that most
                         // XHR requests won't hit it, but not so big that it allows for DOS
-                        let size = cmp::min(0b100_0000000000_0000000000, len.0 as usize);
+                        let size = cmp::min(0b100_0000000000_0000000000, len as usize);
 
                         // preallocate the buffer
                         response.reserve(size);
@@ -1089,6 +2167,7 @@ impl XMLHttpRequest {
                 }
                 // Substep 3
                 if !self.sync.get() {
+                    self.set_stall_timeout(self.body_idle_timeout.get());
                     self.change_ready_state(XMLHttpRequestState::HeadersReceived);
                 }
             },
@@ -1097,10 +2176,33 @@ impl XMLHttpRequest {
                 // Part of step 11, send() (processing response body)
                 // XXXManishearth handle errors, if any (substep 2)
 
+                // An empty chunk has no new data to report, so it neither
+                // moves to the loading state nor fires any event.
+                if partial_response.is_empty() {
+                    return;
+                }
+                // The body is always delivered after the headers, but don't
+                // rely on that: a chunk arriving before them can't be
+                // reported without skipping the HEADERS_RECEIVED state.
+                let expecting_body = match self.ready_state.get() {
+                    XMLHttpRequestState::HeadersReceived | XMLHttpRequestState::Loading => true,
+                    // Synchronous requests stay opened until they're done.
+                    XMLHttpRequestState::Opened => self.sync.get(),
+                    XMLHttpRequestState::Unsent | XMLHttpRequestState::Done => false,
+                };
+                if !expecting_body {
+                    warn!("Ignoring a response chunk received before the headers");
+                    return;
+                }
+                for sink in self.response_sinks.borrow().iter() {
+                    sink.write(self, &partial_response);
+                }
                 self.response.borrow_mut().append(&mut partial_response);
+                self.update_throughput();
                 if !self.sync.get() {
+                    self.set_stall_timeout(self.body_idle_timeout.get());
                     if self.ready_state.get() == XMLHttpRequestState::HeadersReceived {
-                        self.ready_state.set(XMLHttpRequestState::Loading);
+                        self.set_ready_state(XMLHttpRequestState::Loading);
                     }
                     let event = Event::new(
                         &self.global(),
@@ -1121,6 +2223,7 @@ impl XMLHttpRequest {
                 );
 
                 self.cancel_timeout();
+                self.cancel_stall_timeout();
                 self.canceller.borrow_mut().ignore();
 
                 // Part of step 11, send() (processing response end of file)
@@ -1128,6 +2231,11 @@ impl XMLHttpRequest {
 
                 // Subsubsteps 6-8
                 self.send_flag.set(false);
+                self.stop_tracking();
+                self.release_cancel_target();
+
+                self.end_response_sinks(None);
+                self.notify_request_completed();
 
                 self.change_ready_state(XMLHttpRequestState::Done);
                 return_if_fetch_was_terminated!();
@@ -1138,10 +2246,18 @@ impl XMLHttpRequest {
             },
             XHRProgress::Errored(_, e) => {
                 self.cancel_timeout();
+                self.cancel_stall_timeout();
+                self.clear_throttled_body();
                 self.canceller.borrow_mut().ignore();
 
                 self.discard_subsequent_responses();
                 self.send_flag.set(false);
+                self.stop_tracking();
+                self.release_cancel_target();
+                self.end_response_sinks(Some(&e));
+                for observer in self.completion_observers.borrow().iter() {
+                    observer.request_failed(self, &e);
+                }
                 // XXXManishearth set response to NetworkError
                 self.change_ready_state(XMLHttpRequestState::Done);
                 return_if_fetch_was_terminated!();
@@ -1167,13 +2283,465 @@ impl XMLHttpRequest {
         }
     }
 
+    /// Whether a request was sent and hasn't completed yet.
+    pub fn in_flight(&self) -> bool {
+        self.send_flag.get()
+    }
+
+    /// The generation of the current request, which is incremented every time
+    /// an ongoing fetch is terminated by `open()` or `abort()`.
+    pub fn generation_id(&self) -> GenerationId {
+        self.generation_id.get()
+    }
+
+    /// Whether the current response was served from the HTTP cache, either
+    /// directly or after a successful revalidation.
+    pub fn response_from_cache(&self) -> bool {
+        self.response_from_cache.get()
+    }
+
+    /// Register an embedder observer notified of every `readyState` change.
+    pub fn add_ready_state_observer(&self, observer: Box<dyn ReadyStateObserver>) {
+        self.ready_state_observers.borrow_mut().push(observer);
+    }
+
+    /// Register an embedder observer notified when a request ends.
+    pub fn add_completion_observer(&self, observer: Box<dyn CompletionObserver>) {
+        self.completion_observers.borrow_mut().push(observer);
+    }
+
+    /// Register an embedder observer notified when a request is sent.
+    pub fn add_request_start_observer(&self, observer: Box<dyn RequestStartObserver>) {
+        self.request_start_observers.borrow_mut().push(observer);
+    }
+
+    /// Mask the values of the `names` headers, e.g. `Authorization` or
+    /// `Cookie`, in what request start observers see. The headers sent are
+    /// unaffected.
+    pub fn set_redacted_request_headers(&self, names: Vec<HeaderName>) {
+        *self.redacted_request_headers.borrow_mut() = names;
+    }
+
+    /// A stream of the response body, which can be consumed from any thread
+    /// as chunks arrive. It ends with the request that is ongoing or, if
+    /// there is none, the next one, and fails if the XHR is reset first. Like
+    /// a sink added with `add_response_sink`, it only sees the chunks that
+    /// arrive afterwards.
+    pub fn response_stream(&self) -> Receiver<ResponseStreamMsg> {
+        let (sender, receiver) = unbounded();
+        self.add_response_sink(Box::new(ChannelSink(RefCell::new(Some(sender)))));
+        receiver
+    }
+
+    /// A handle to abort the request in flight from another thread. It does
+    /// nothing once that request has finished, or if there was none.
+    pub fn cancel_handle(&self) -> XHRCancelHandle {
+        let global = self.global();
+        let target = if self.send_flag.get() {
+            self.cancel_target
+                .borrow_mut()
+                .get_or_insert_with(|| CancelTarget(Arc::new(Mutex::new(Some(Trusted::new(self))))))
+                .clone()
+        } else {
+            CancelTarget(Arc::new(Mutex::new(None)))
+        };
+        XHRCancelHandle {
+            xhr: target,
+            generation_id: self.generation_id.get(),
+            task_source: global.networking_task_source(),
+            canceller: global.task_canceller(TaskSourceName::Networking),
+        }
+    }
+
+    /// Register an embedder sink receiving a copy of the response body of
+    /// the request that is ongoing or, if there is none, the next one.
+    pub fn add_response_sink(&self, sink: Box<dyn ResponseSink>) {
+        self.response_sinks.borrow_mut().push(sink);
+    }
+
+    /// Share `cache` between this XHR and others, or stop using one with
+    /// `None`. See `EtagCache`.
+    pub fn set_etag_cache(&self, cache: Option<Rc<EtagCache>>) {
+        *self.etag_cache.borrow_mut() = cache;
+    }
+
+    /// Set the embedder hook rewriting the URL of subsequent requests.
+    pub fn set_url_rewriter(&self, rewriter: Option<Box<dyn UrlRewriter>>) {
+        *self.url_rewriter.borrow_mut() = rewriter;
+    }
+
+    /// The request headers of the last request, after `send()` added its
+    /// defaults such as `Accept` and `Content-Type` to the author's. This is
+    /// empty until then, and unless recording them was turned on with
+    /// `set_record_sent_request_headers`. The network stack may still add
+    /// others, such as `Origin` or cookies.
+    pub fn sent_request_headers(&self) -> HeaderMap {
+        self.sent_request_headers.borrow().clone()
+    }
+
+    /// Whether the headers of subsequent requests are kept around for
+    /// `sent_request_headers`. This is off by default so that they aren't
+    /// copied for every request, unless the testing pref is set.
+    pub fn set_record_sent_request_headers(&self, record: bool) {
+        self.record_sent_request_headers.set(record);
+    }
+
+    /// The raw `Set-Cookie` header values of the response, which script never
+    /// sees. This is only meant for embedders syncing a native cookie store,
+    /// and is empty unless recording them was turned on with
+    /// `set_record_response_set_cookies`.
+    pub fn response_set_cookies(&self) -> Vec<Vec<u8>> {
+        self.response_set_cookies.borrow().clone()
+    }
+
+    /// Whether the `Set-Cookie` headers of subsequent responses are kept
+    /// around for `response_set_cookies`. This is off by default so that they
+    /// aren't copied for every response, unless the testing pref is set.
+    pub fn set_record_response_set_cookies(&self, record: bool) {
+        self.record_response_set_cookies.set(record);
+    }
+
+    /// The decoded response body, for native consumers that want the text
+    /// without going through the `responseText` getter. Unlike the getter,
+    /// this ignores `responseType`, but it's empty until the request is done.
+    /// It fails the same way if the body is over the size limit for text.
+    pub fn response_text(&self) -> Fallible<String> {
+        if self.ready_state.get() != XMLHttpRequestState::Done {
+            return Ok(String::new());
+        }
+        self.text_response()
+    }
+
+    /// The average number of response body bytes received per second since
+    /// the request was sent, updated as chunks arrive. This is 0 until the
+    /// first chunk, and stays as it was after the last one. It is also 0
+    /// unless measuring it was turned on with `set_measure_throughput`.
+    pub fn throughput(&self) -> f64 {
+        self.throughput.get()
+    }
+
+    /// Whether the throughput of subsequent requests is measured. This is off
+    /// by default so that the clock isn't read for every chunk, unless the
+    /// testing pref is set.
+    pub fn set_measure_throughput(&self, measure: bool) {
+        self.measure_throughput.set(measure);
+    }
+
+    /// The number of bytes of the response body received so far, so that a
+    /// consumer can decide whether to read them now or wait for more.
+    pub fn buffered_len(&self) -> usize {
+        self.response.borrow().len()
+    }
+
+    /// A copy of the bytes `start..end` of the response body received so far,
+    /// e.g. for a media player seeking within a buffered response. The range
+    /// is clamped to the received bytes, so it may be shorter or empty.
+    pub fn response_slice(&self, start: usize, end: usize) -> Vec<u8> {
+        let response = self.response.borrow();
+        let end = cmp::min(end, response.len());
+        let start = cmp::min(start, end);
+        response[start..end].to_vec()
+    }
+
+    /// Whether a CORS-preflight request was sent before the current response
+    /// was fetched, which helps diagnosing blocked cross-origin requests.
+    pub fn cors_preflight_performed(&self) -> bool {
+        self.cors_preflight_performed.get()
+    }
+
+    /// Whether the current response came over a connection that was opened
+    /// for an earlier request, to tell apart the cost of connection setup.
+    pub fn connection_reused(&self) -> bool {
+        self.connection_reused.get()
+    }
+
+    /// The TLS connection the current response was received over, for https
+    /// responses. This is only meant for embedders showing security details,
+    /// and is `None` unless recording it was turned on with
+    /// `set_record_connection_details`.
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        self.tls_info.borrow().clone()
+    }
+
+    /// Whether subsequent responses keep the details of the connection they
+    /// were received over, for `tls_info` and `status_line`. This is off by default so that
+    /// the network doesn't copy them for every response, unless the testing
+    /// pref is set.
+    pub fn set_record_connection_details(&self, record: bool) {
+        self.record_connection_details.set(record);
+    }
+
+    fn records_connection_details(&self) -> bool {
+        self.record_connection_details.get() || pref!(dom.xhr.testing.enabled)
+    }
+
+    /// The number of redirects followed to get the current response, once it
+    /// was fully received. Like the `redirectCount` of navigation timing, this
+    /// is 0 if a cross-origin redirect failed the timing allow check.
+    pub fn redirect_count(&self) -> u16 {
+        self.redirect_count.get()
+    }
+
+    /// The URLs of the current request, from the one passed to `open()` to
+    /// the one the response came from, each with the status of its response.
+    /// Unlike `responseURL`, this includes credentials and fragments. This is
+    /// only meant for embedders' debuggers, and is empty unless recording it
+    /// was turned on with `set_record_redirect_chain`.
+    pub fn redirect_chain(&self) -> Vec<(ServoUrl, u16)> {
+        self.redirect_chain.borrow().clone()
+    }
+
+    /// Whether subsequent requests keep the URLs they were redirected from,
+    /// for `redirect_chain`. This is off by default so that the network
+    /// doesn't record them for every request, unless the testing pref is set.
+    pub fn set_record_redirect_chain(&self, record: bool) {
+        self.record_redirect_chain.set(record);
+    }
+
+    fn records_redirect_chain(&self) -> bool {
+        self.record_redirect_chain.get() || pref!(dom.xhr.testing.enabled)
+    }
+
+    /// The status line of the current response, e.g. `HTTP/1.1 200 OK`, for
+    /// responses that came from the network. HTTP/2 has no reason phrase, so
+    /// the line ends with the status code there. This is only meant for
+    /// embedders' debugging tools, and is `None` unless recording it was
+    /// turned on with `set_record_connection_details`.
+    pub fn status_line(&self) -> Option<Vec<u8>> {
+        let version = self.http_version.borrow();
+        let version = version.as_ref()?;
+        let mut line = format!("{} {}", version, self.status.get()).into_bytes();
+        if version.starts_with("HTTP/1") || version.starts_with("HTTP/0") {
+            line.push(b' ');
+            line.extend_from_slice(&self.status_text.borrow());
+        }
+        Some(line)
+    }
+
+    /// Whether redirects are returned to the caller rather than followed.
+    /// This is only meant for embedders, and applies to subsequent requests.
+    pub fn set_manual_redirect(&self, manual: bool) {
+        self.manual_redirect.set(manual);
+    }
+
+    /// An absolute instant by which subsequent requests must complete, on top
+    /// of the relative `timeout`: whichever comes first times the request
+    /// out. This is only meant for embedders coordinating several requests.
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        self.deadline.set(deadline);
+    }
+
+    /// Time subsequent asynchronous requests out when their response headers
+    /// take longer than `timeout` to arrive after `send()`. Unlike the web
+    /// facing `timeout`, this doesn't limit the time spent on the body.
+    pub fn set_headers_timeout(&self, timeout: Option<Duration>) {
+        self.headers_timeout.set(timeout);
+    }
+
+    /// Time subsequent asynchronous requests out when no part of the body
+    /// arrives for `timeout`, counting from the headers and then from each
+    /// chunk. A slow body that keeps making progress is never timed out.
+    pub fn set_body_idle_timeout(&self, timeout: Option<Duration>) {
+        self.body_idle_timeout.set(timeout);
+    }
+
+    /// Deliver the response body of subsequent asynchronous requests to script
+    /// at no more than `bytes_per_second`, holding back what arrives faster,
+    /// so progress events come at a controlled rate. This is only meant for
+    /// testing progressive loading, and doesn't slow down the network.
+    pub fn set_bandwidth_limit(&self, bytes_per_second: Option<u64>) {
+        self.bandwidth_limit.set(bytes_per_second);
+    }
+
+    /// Whether `response` is parsed according to the response's MIME type when
+    /// `responseType` is the empty string: JSON as `json`, HTML and XML as
+    /// `document`, and images as `blob`. This only applies once the response
+    /// is complete; `responseType` and `responseText` are unaffected. This is
+    /// only meant for embedders' generic fetch helpers.
+    pub fn set_auto_response_type(&self, auto: bool) {
+        self.auto_response_type.set(auto);
+    }
+
+    /// Whether the body of subsequent requests is sent gzip-compressed, with
+    /// a `Content-Encoding: gzip` header. This is only meant for embedders
+    /// that know the server accepts compressed request bodies.
+    pub fn set_compress_request_body(&self, compress: bool) {
+        self.compress_request_body.set(compress);
+    }
+
+    /// Whether subsequent requests keep going once the global that sent them
+    /// is torn down, like `fetch()` with `keepalive`. Their body is limited to
+    /// 64 KiB. This is only meant for embedders, e.g. for analytics on unload.
+    pub fn set_keep_alive(&self, keep_alive: bool) {
+        self.keep_alive.set(keep_alive);
+    }
+
+    /// Subresource integrity metadata the response of subsequent requests is
+    /// verified against; a mismatch results in a network error. This is only
+    /// meant for embedders, XHR doesn't support integrity checks otherwise.
+    pub fn set_integrity_metadata(&self, integrity_metadata: String) {
+        *self.integrity_metadata.borrow_mut() = integrity_metadata;
+    }
+
+    /// Send `referrer` instead of the document's referrer with subsequent
+    /// requests, or go back to the document's with `None`. The referrer
+    /// policy still applies to it.
+    pub fn set_referrer_override(&self, referrer: Option<Referrer>) {
+        *self.referrer_override.borrow_mut() = referrer;
+    }
+
+    /// Send `user_agent` as the `User-Agent` of subsequent requests instead of
+    /// the default one, or go back to the default with `None`. Script can't
+    /// set this header itself. Fails if the value isn't a valid header value.
+    pub fn set_user_agent(&self, user_agent: Option<String>) -> ErrorResult {
+        if let Some(ref user_agent) = user_agent {
+            HeaderValue::from_str(user_agent).map_err(|_| Error::Syntax)?;
+        }
+        *self.user_agent.borrow_mut() = user_agent;
+        Ok(())
+    }
+
+    /// The reason the last request failed with a network error. Script only
+    /// ever sees a generic "error" event, but embedders may want the detail.
+    pub fn last_network_error_reason(&self) -> Option<String> {
+        self.network_error
+            .borrow()
+            .as_ref()
+            .map(|error| match *error {
+                NetworkError::Internal(ref reason) => reason.clone(),
+                NetworkError::LoadCancelled => "load cancelled".to_owned(),
+                NetworkError::SslValidation(_, ref reason) => reason.clone(),
+                NetworkError::ContentSecurityPolicy => {
+                    "Blocked by Content-Security-Policy".to_owned()
+                },
+            })
+    }
+
+    /// Whether the last request failed because the Content Security Policy
+    /// of the global blocked it, rather than a failure of the network.
+    pub fn blocked_by_csp(&self) -> bool {
+        *self.network_error.borrow() == Some(NetworkError::ContentSecurityPolicy)
+    }
+
+    /// Set the response to a network error, dropping the previous response's
+    /// body and any response object computed from it.
+    fn reset_response(&self) {
+        self.status.set(0);
+        *self.status_text.borrow_mut() = ByteString::new(vec![]);
+        self.response_url.borrow_mut().clear();
+        *self.response_headers.borrow_mut() = HeaderMap::new();
+        self.response_set_cookies.borrow_mut().clear();
+        self.response_from_cache.set(false);
+        self.cors_preflight_performed.set(false);
+        self.connection_reused.set(false);
+        *self.tls_info.borrow_mut() = None;
+        *self.http_version.borrow_mut() = None;
+        self.redirect_count.set(0);
+        self.redirect_chain.borrow_mut().clear();
+        self.throughput.set(0.0);
+        *self.network_error.borrow_mut() = None;
+        self.response.borrow_mut().clear();
+        self.response_xml.set(None);
+        self.response_blob.set(None);
+        self.response_arraybuffer.set(ptr::null_mut());
+        self.response_json.set(NullValue());
+        // Sinks added for the previous request don't carry over.
+        self.end_response_sinks(Some(&Error::Abort));
+        *self.teed_response.borrow_mut() = None;
+        *self.response_stream.borrow_mut() = None;
+    }
+
+    /// Tell the response sinks how their request ended, and drop them.
+    fn end_response_sinks(&self, error: Option<&Error>) {
+        let sinks = mem::replace(&mut *self.response_sinks.borrow_mut(), vec![]);
+        for sink in sinks {
+            match error {
+                Some(error) => sink.fail(self, error),
+                None => sink.finish(self),
+            }
+        }
+    }
+
+    fn update_throughput(&self) {
+        let elapsed = match self.send_time.get() {
+            Some(send_time) => send_time.elapsed().as_secs_f64(),
+            None => return,
+        };
+        if elapsed > 0.0 {
+            let received = self.response.borrow().len() as f64;
+            self.throughput.set(received / elapsed);
+        }
+    }
+
+    /// Remember a complete 200 response to a GET with an `ETag` in the
+    /// embedder's ETag cache, if there is one.
+    fn cache_etag_response(&self, gen_id: GenerationId) {
+        let cache = match *self.etag_cache.borrow() {
+            Some(ref cache) => cache.clone(),
+            None => return,
+        };
+        if gen_id != self.generation_id.get() ||
+            self.response_status.get().is_err() ||
+            self.status.get() != 200 ||
+            *self.request_method.borrow() != Method::GET
+        {
+            return;
+        }
+        let headers = self.response_headers.borrow();
+        let url = match *self.request_url.borrow() {
+            Some(ref url) if headers.contains_key(header::ETAG) => url.clone(),
+            _ => return,
+        };
+        let entry = EtagCacheEntry {
+            status: self.status.get(),
+            status_text: self.status_text.borrow().to_vec(),
+            headers: headers.clone(),
+            body: self.response.borrow().clone(),
+        };
+        cache.entries.borrow_mut().insert(url, entry);
+    }
+
+    fn notify_request_completed(&self) {
+        let observers = self.completion_observers.borrow();
+        if observers.is_empty() {
+            return;
+        }
+        let headers = self.filter_response_headers();
+        let status_text = self.status_text.borrow();
+        let length = self.response.borrow().len();
+        for observer in observers.iter() {
+            observer.request_completed(self, self.status.get(), &status_text, &headers, length);
+        }
+    }
+
     fn terminate_ongoing_fetch(&self) {
         self.canceller.borrow_mut().cancel();
+        self.clear_throttled_body();
+        self.stop_tracking();
+        self.release_cancel_target();
         let GenerationId(prev_id) = self.generation_id.get();
         self.generation_id.set(GenerationId(prev_id + 1));
         self.response_status.set(Ok(()));
     }
 
+    fn start_tracking(&self) {
+        if !self.tracked.replace(true) {
+            self.global().track_xhr(self);
+        }
+    }
+
+    fn stop_tracking(&self) {
+        if self.tracked.replace(false) {
+            self.global().untrack_xhr(self);
+        }
+    }
+
+    fn release_cancel_target(&self) {
+        if let Some(target) = self.cancel_target.borrow_mut().take() {
+            *target.0.lock().unwrap() = None;
+        }
+    }
+
     fn dispatch_progress_event(&self, upload: bool, type_: Atom, loaded: u64, total: Option<u64>) {
         let (total_length, length_computable) = if self
             .response_headers
@@ -1210,14 +2778,50 @@ impl XMLHttpRequest {
 
     fn dispatch_response_progress_event(&self, type_: Atom) {
         let len = self.response.borrow().len() as u64;
-        let total = self
-            .response_headers
-            .borrow()
-            .typed_get::<ContentLength>()
-            .map(|v| v.0);
+        // The total is the advertised length, which can exceed the capacity
+        // reserved for the response buffer in the HeadersReceived step.
+        let total = self.response_content_length();
         self.dispatch_progress_event(false, type_, len, total);
     }
 
+    /// The length of the response body advertised by `Content-Length`, if it
+    /// can be trusted.
+    fn response_content_length(&self) -> Option<u64> {
+        // A Content-Length sent along with a null body status describes the
+        // selected representation, not this response's body.
+        if self.has_null_body_status() {
+            return None;
+        }
+        let headers = self.response_headers.borrow();
+        // A message with both is malformed, and its Transfer-Encoding framing
+        // wins, as in browsers: https://tools.ietf.org/html/rfc7230#section-3.3.3
+        if headers.contains_key(header::TRANSFER_ENCODING) {
+            return None;
+        }
+        headers.typed_get::<ContentLength>().map(|len| len.0)
+    }
+
+    /// Whether the global can still run a fetch: a window's document must be
+    /// fully active, and a worker must not be closing.
+    fn global_is_active(&self) -> bool {
+        let global = self.global();
+        if let Some(window) = global.downcast::<Window>() {
+            return window.Document().is_fully_active();
+        }
+        if let Some(worker) = global.downcast::<WorkerGlobalScope>() {
+            return !worker.is_closing();
+        }
+        true
+    }
+
+    /// <https://fetch.spec.whatwg.org/#null-body-status>
+    fn has_null_body_status(&self) -> bool {
+        match self.status.get() {
+            101 | 204 | 205 | 304 => true,
+            _ => false,
+        }
+    }
+
     fn set_timeout(&self, duration_ms: u32) {
         // Sets up the object to timeout in a given number of milliseconds
         // This will cancel all previous timeouts
@@ -1230,24 +2834,111 @@ impl XMLHttpRequest {
             Some(self.global().schedule_callback(callback, duration));
     }
 
+    /// The number of milliseconds left before the ongoing request times out,
+    /// if it has a `timeout` or a deadline at all.
+    fn remaining_timeout(&self) -> Option<u32> {
+        let timeout = match self.timeout.get() {
+            0 => None,
+            timeout => {
+                let progress = time::now().to_timespec().sec - self.fetch_time.get();
+                Some(timeout.saturating_sub((progress * 1000) as u32))
+            },
+        };
+        let deadline = self.deadline.get().map(|deadline| {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            cmp::min(remaining.as_millis(), u32::max_value() as u128) as u32
+        });
+        match (timeout, deadline) {
+            (Some(timeout), Some(deadline)) => Some(cmp::min(timeout, deadline)),
+            (timeout, deadline) => timeout.or(deadline),
+        }
+    }
+
     fn cancel_timeout(&self) {
         if let Some(handle) = self.timeout_cancel.borrow_mut().take() {
             self.global().unschedule_callback(handle);
         }
     }
 
+    /// Restart the timer of the headers or body idle timeout, or only stop it
+    /// with `None`. It runs the same timeout steps as `timeout`.
+    fn set_stall_timeout(&self, timeout: Option<Duration>) {
+        self.cancel_stall_timeout();
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        let callback = OneshotTimerCallback::XhrTimeout(XHRTimeoutCallback {
+            xhr: Trusted::new(self),
+            generation_id: self.generation_id.get(),
+        });
+        let duration = Length::new(timeout.as_millis() as u64);
+        *self.stall_timeout_cancel.borrow_mut() =
+            Some(self.global().schedule_callback(callback, duration));
+    }
+
+    fn cancel_stall_timeout(&self) {
+        if let Some(handle) = self.stall_timeout_cancel.borrow_mut().take() {
+            self.global().unschedule_callback(handle);
+        }
+    }
+
     // https://xhr.spec.whatwg.org/#text-response
-    fn text_response(&self) -> String {
-        // Step 3, 5
-        let charset = self.final_charset().unwrap_or(UTF_8);
-        // TODO: Step 4 - add support for XML encoding guess stuff using XML spec
-
-        // According to Simon, decode() should never return an error, so unwrap()ing
-        // the result should be fine. XXXManishearth have a closer look at this later
-        // Step 1, 2, 6
+    fn text_response(&self) -> Fallible<String> {
+        // Decoding a huge body into a single string could run out of memory,
+        // so refuse to beyond a limit.
+        let max_bytes = pref!(dom.xhr.max_response_text_bytes);
+        let len = self.response.borrow().len();
+        if max_bytes > 0 && len as i64 > max_bytes {
+            warn!(
+                "response text of {} bytes exceeds the limit of {} bytes",
+                len, max_bytes
+            );
+            return Err(Error::QuotaExceeded);
+        }
+        // Step 4, a response without a MIME type is text/xml
+        let is_xml = self
+            .final_mime_type()
+            .map_or(true, |mime| is_xml_mime(&mime));
+        let declaration =
+            if self.response_type.get() == XMLHttpRequestResponseType::_empty && is_xml {
+                EncodingDeclaration::Xml
+            } else {
+                EncodingDeclaration::None
+            };
+        // Step 1-3, 5, 6
+        Ok(self.decode_response(declaration).0)
+    }
+
+    /// The encoding the body is decoded with, for text and documents alike.
+    /// A BOM takes precedence, then the final charset (an overridden one,
+    /// then the one from `Content-Type`), then an encoding the body declares
+    /// itself, and UTF-8 is the default.
+    fn response_encoding(&self, declaration: EncodingDeclaration) -> &'static Encoding {
+        let response = self.response.borrow();
+        if let Some((encoding, _)) = Encoding::for_bom(&response) {
+            return encoding;
+        }
+        if let Some(charset) = self.final_charset() {
+            return charset;
+        }
+        let declared = match declaration {
+            EncodingDeclaration::None => None,
+            EncodingDeclaration::Xml => xml_declared_encoding(&response),
+            EncodingDeclaration::HtmlMeta => html_meta_encoding(&response),
+        };
+        declared.unwrap_or(UTF_8)
+    }
+
+    /// Decodes the body with `response_encoding`, which is also returned.
+    fn decode_response(&self, declaration: EncodingDeclaration) -> (String, &'static Encoding) {
+        let encoding = self.response_encoding(declaration);
+        // Malformed byte sequences are replaced with U+FFFD, including an
+        // incomplete sequence at the end of a partially received body, so
+        // no bytes are ever dropped from the decoded text.
         let response = self.response.borrow();
-        let (text, _, _) = charset.decode(&response);
-        text.into_owned()
+        let (text, _) = encoding.decode_with_bom_removal(&response);
+        (text.into_owned(), encoding)
     }
 
     // https://xhr.spec.whatwg.org/#blob-response
@@ -1306,13 +2997,11 @@ impl XMLHttpRequest {
         }
 
         let mime_type = self.final_mime_type();
-        // TODO: prescan the response to determine encoding if final charset is null
-        let charset = self.final_charset().unwrap_or(UTF_8);
         let temp_doc: DomRoot<Document>;
         match mime_type {
             Some(ref mime) if mime.type_() == mime::TEXT && mime.subtype() == mime::HTML => {
                 // Step 5
-                if self.response_type.get() == XMLHttpRequestResponseType::_empty {
+                if self.effective_response_type() == XMLHttpRequestResponseType::_empty {
                     return None;
                 } else {
                     // Step 6
@@ -1326,6 +3015,7 @@ impl XMLHttpRequest {
             {
                 temp_doc = self.handle_xml();
             }
+            // A response without a MIME type is text/xml, it isn't sniffed
             None => {
                 temp_doc = self.handle_xml();
             },
@@ -1337,8 +3027,6 @@ impl XMLHttpRequest {
                 return None;
             },
         }
-        // Step 9
-        temp_doc.set_encoding(charset);
         // Step 13
         self.response_xml.set(Some(&temp_doc));
         return self.response_xml.get();
@@ -1387,6 +3075,8 @@ impl XMLHttpRequest {
                 json_text.len() as u32,
                 rval.handle_mut(),
             ) {
+                // Nothing is cached on failure, so that a later response
+                // of a reused XHR is parsed afresh.
                 JS_ClearPendingException(*cx);
                 return NullValue();
             }
@@ -1397,22 +3087,22 @@ impl XMLHttpRequest {
     }
 
     fn document_text_html(&self) -> DomRoot<Document> {
-        let charset = self.final_charset().unwrap_or(UTF_8);
         let wr = self.global();
-        let response = self.response.borrow();
-        let (decoded, _, _) = charset.decode(&response);
+        let (decoded, encoding) = self.decode_response(EncodingDeclaration::HtmlMeta);
         let document = self.new_doc(IsHTMLDocument::HTMLDocument);
+        // Step 9
+        document.set_encoding(encoding);
         // TODO: Disable scripting while parsing
         ServoParser::parse_html_document(&document, DOMString::from(decoded), wr.get_url());
         document
     }
 
     fn handle_xml(&self) -> DomRoot<Document> {
-        let charset = self.final_charset().unwrap_or(UTF_8);
         let wr = self.global();
-        let response = self.response.borrow();
-        let (decoded, _, _) = charset.decode(&response);
+        let (decoded, encoding) = self.decode_response(EncodingDeclaration::Xml);
         let document = self.new_doc(IsHTMLDocument::NonHTMLDocument);
+        // Step 9
+        document.set_encoding(encoding);
         // TODO: Disable scripting while parsing
         ServoParser::parse_xml_document(&document, DOMString::from(decoded), wr.get_url());
         document
@@ -1428,7 +3118,7 @@ impl XMLHttpRequest {
             Ok(parsed) => Some(parsed),
             Err(_) => None, // Step 7
         };
-        let content_type = self.final_mime_type();
+        let content_type = self.final_mime_type().or(Some(mime::TEXT_XML));
         Document::new(
             win,
             HasBrowsingContext::No,
@@ -1460,6 +3150,9 @@ impl XMLHttpRequest {
     }
 
     fn fetch(&self, init: RequestBuilder, global: &GlobalScope) -> ErrorResult {
+        if self.record_sent_request_headers.get() || pref!(dom.xhr.testing.enabled) {
+            *self.sent_request_headers.borrow_mut() = init.headers.clone();
+        }
         let xhr = Trusted::new(self);
 
         let context = Arc::new(Mutex::new(XHRContext {
@@ -1478,6 +3171,11 @@ impl XMLHttpRequest {
         };
 
         let cancel_receiver = self.canceller.borrow_mut().initialize();
+        if self.keep_alive.get() {
+            // Don't cancel the fetch when the XHR goes away with its global,
+            // but still let abort() and timeouts cancel it.
+            self.canceller.borrow_mut().keep_alive();
+        }
 
         XMLHttpRequest::initiate_async_xhr(
             context.clone(),
@@ -1507,14 +3205,43 @@ impl XMLHttpRequest {
             match self.response_headers.borrow().typed_get::<ContentType>() {
                 Some(ct) => {
                     let mime: Mime = ct.into();
-                    let value = mime.get_param(mime::CHARSET);
-                    value.and_then(|value| Encoding::for_label(value.as_ref().as_bytes()))
+                    charset_encoding(&mime)
                 },
                 None => None,
             }
         }
     }
 
+    /// The response type `response` is produced for, which differs from
+    /// `responseType` only for automatically selected types.
+    fn effective_response_type(&self) -> XMLHttpRequestResponseType {
+        let response_type = self.response_type.get();
+        if !self.auto_response_type.get() ||
+            response_type != XMLHttpRequestResponseType::_empty ||
+            self.ready_state.get() != XMLHttpRequestState::Done
+        {
+            return response_type;
+        }
+        let mime = match self.final_mime_type() {
+            Some(mime) => mime,
+            None => return response_type,
+        };
+        let is = |name: Name| mime.subtype() == name || mime.suffix() == Some(name);
+        let is_html = mime.type_() == mime::TEXT && mime.subtype() == mime::HTML;
+        let is_xml =
+            (mime.type_() == mime::TEXT || mime.type_() == mime::APPLICATION) && is(mime::XML);
+        if mime.type_() == mime::APPLICATION && is(mime::JSON) {
+            XMLHttpRequestResponseType::Json
+        } else if mime.type_() == mime::IMAGE {
+            XMLHttpRequestResponseType::Blob
+        } else if (is_html || is_xml) && !self.global().is::<WorkerGlobalScope>() {
+            // Documents aren't available in workers.
+            XMLHttpRequestResponseType::Document
+        } else {
+            response_type
+        }
+    }
+
     fn final_mime_type(&self) -> Option<Mime> {
         if self.override_mime_type.borrow().is_some() {
             self.override_mime_type.borrow().clone()
@@ -1537,12 +3264,74 @@ pub struct XHRTimeoutCallback {
 impl XHRTimeoutCallback {
     pub fn invoke(self) {
         let xhr = self.xhr.root();
-        if xhr.ready_state.get() != XMLHttpRequestState::Done {
+        if xhr.ready_state.get() != XMLHttpRequestState::Done &&
+            xhr.generation_id.get() == self.generation_id
+        {
+            // Terminate the ongoing fetch, including a request body that is
+            // still being uploaded, before running the timeout steps.
+            xhr.canceller.borrow_mut().cancel();
             xhr.process_partial_response(XHRProgress::Errored(self.generation_id, Error::Timeout));
         }
     }
 }
 
+/// Shared by an XHR and the cancel handles of its request in flight. The XHR
+/// empties it when the request finishes, so that the handles don't keep it
+/// alive past that.
+#[derive(Clone)]
+struct CancelTarget(Arc<Mutex<Option<Trusted<XMLHttpRequest>>>>);
+
+#[allow(unsafe_code)]
+unsafe_no_jsmanaged_fields!(CancelTarget);
+
+/// A handle to abort the request of an XHR from any thread, for embedders that
+/// shouldn't hold on to the DOM object.
+pub struct XHRCancelHandle {
+    xhr: CancelTarget,
+    generation_id: GenerationId,
+    task_source: NetworkingTaskSource,
+    canceller: TaskCanceller,
+}
+
+impl XHRCancelHandle {
+    /// Queue a task aborting the request as `abort()` does, if it is still in
+    /// flight by the time the task runs. Nothing happens once the request has
+    /// finished, the XHR has moved on to another one, or its global is gone.
+    pub fn cancel(&self) {
+        if self.xhr.0.lock().unwrap().is_none() {
+            return;
+        }
+        let target = self.xhr.clone();
+        let generation_id = self.generation_id;
+        let _ = self.task_source.queue_with_canceller(
+            task!(cancel_xhr: move || {
+                // Aborting empties the target, so don't hold its lock.
+                let xhr = match *target.0.lock().unwrap() {
+                    Some(ref xhr) => xhr.root(),
+                    None => return,
+                };
+                if xhr.generation_id.get() == generation_id {
+                    xhr.Abort();
+                }
+            }),
+            &self.canceller,
+        );
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+pub struct XHRThrottleCallback {
+    #[ignore_malloc_size_of = "Because it is non-owning"]
+    xhr: Trusted<XMLHttpRequest>,
+    generation_id: GenerationId,
+}
+
+impl XHRThrottleCallback {
+    pub fn invoke(self) {
+        self.xhr.root().release_throttled_body(self.generation_id);
+    }
+}
+
 pub trait Extractable {
     fn extract(&self) -> (Vec<u8>, Option<DOMString>);
 }
@@ -1570,8 +3359,7 @@ impl Extractable for DOMString {
 
 impl Extractable for FormData {
     fn extract(&self) -> (Vec<u8>, Option<DOMString>) {
-        let boundary = generate_boundary();
-        let bytes = encode_multipart_form_data(&mut self.datums(), boundary.clone(), UTF_8);
+        let (bytes, boundary) = encode_multipart_form_data(&mut self.datums(), UTF_8);
         (
             bytes,
             Some(DOMString::from(format!(
@@ -1596,11 +3384,61 @@ impl Extractable for URLSearchParams {
 fn serialize_document(doc: &Document) -> Fallible<DOMString> {
     let mut writer = vec![];
     match serialize(&mut writer, &doc.upcast::<Node>(), SerializeOpts::default()) {
-        Ok(_) => Ok(DOMString::from(String::from_utf8(writer).unwrap())),
+        Ok(_) => {
+            let serialized = String::from_utf8(writer).unwrap();
+            if doc.is_html_document() {
+                Ok(DOMString::from(serialized))
+            } else {
+                Ok(DOMString::from(normalize_xml_declaration_encoding(
+                    serialized,
+                )))
+            }
+        },
         Err(_) => Err(Error::InvalidState),
     }
 }
 
+/// The serialized document is always sent UTF-8 encoded, so rewrite the
+/// encoding of a leading XML declaration to match.
+fn normalize_xml_declaration_encoding(serialized: String) -> String {
+    let is_declaration = serialized.starts_with("<?xml") &&
+        serialized[5..]
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_whitespace());
+    if !is_declaration {
+        return serialized;
+    }
+    let declaration = match serialized.find('>') {
+        Some(end) => &serialized[..end],
+        None => return serialized,
+    };
+    // EncodingDecl ::= S 'encoding' S? '=' S? ('"' EncName '"' | "'" EncName "'")
+    let after_name = match declaration.find("encoding") {
+        Some(start) => start + "encoding".len(),
+        None => return serialized,
+    };
+    let rest = declaration[after_name..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+    if !rest.starts_with('=') {
+        return serialized;
+    }
+    let rest = rest[1..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let value_start = declaration.len() - rest.len();
+    let quote = match rest.chars().next() {
+        Some(quote @ '"') | Some(quote @ '\'') => quote,
+        _ => return serialized,
+    };
+    let value_end = match declaration[value_start + 1..].find(quote) {
+        Some(len) => value_start + 1 + len,
+        None => return serialized,
+    };
+    format!(
+        "{}UTF-8{}",
+        &serialized[..value_start + 1],
+        &serialized[value_end..]
+    )
+}
+
 impl Extractable for BodyInit {
     // https://fetch.spec.whatwg.org/#concept-bodyinit-extract
     fn extract(&self) -> (Vec<u8>, Option<DOMString>) {
@@ -1615,8 +3453,115 @@ impl Extractable for BodyInit {
     }
 }
 
+/// Compresses a request body for `Content-Encoding: gzip`.
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(bytes.len()), Compression::default());
+    // Writing to a Vec can't fail.
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Where the body of a response may declare its own encoding.
+#[derive(Clone, Copy)]
+enum EncodingDeclaration {
+    /// Nowhere, a declaration is ignored.
+    None,
+    /// In a leading XML declaration.
+    Xml,
+    /// In a `<meta>` element within the first 1024 bytes.
+    HtmlMeta,
+}
+
+/// <https://mimesniff.spec.whatwg.org/#xml-mime-type>
+fn is_xml_mime(mime: &Mime) -> bool {
+    ((mime.type_() == mime::TEXT || mime.type_() == mime::APPLICATION) &&
+        mime.subtype() == mime::XML) ||
+        mime.suffix() == Some(mime::XML)
+}
+
+/// The encoding named by the XML declaration starting `body`, if any.
+fn xml_declared_encoding(body: &[u8]) -> Option<&'static Encoding> {
+    if !body.starts_with(b"<?xml") {
+        return None;
+    }
+    let end = body.iter().take(1024).position(|&b| b == b'>')?;
+    declared_value(&body[..end], b"encoding").and_then(encoding_for_declared_label)
+}
+
+/// The encoding named by a `<meta>` element in the first 1024 bytes of
+/// `body`, either by its `charset` attribute or in its `content`. This is a
+/// simplified version of <https://html.spec.whatwg.org/multipage/#prescan-a-byte-stream-to-determine-its-encoding>.
+fn html_meta_encoding(body: &[u8]) -> Option<&'static Encoding> {
+    let prefix = body[..cmp::min(body.len(), 1024)].to_ascii_lowercase();
+    let mut rest = &prefix[..];
+    while let Some(start) = find_bytes(rest, b"<meta") {
+        let tag = &rest[start + b"<meta".len()..];
+        let end = tag.iter().position(|&b| b == b'>').unwrap_or(tag.len());
+        let encoding =
+            declared_value(&tag[..end], b"charset").and_then(encoding_for_declared_label);
+        if encoding.is_some() {
+            return encoding;
+        }
+        rest = &tag[end..];
+    }
+    None
+}
+
+/// The value following the first `name=` in `text`, quoted or not.
+fn declared_value<'a>(text: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let trim_start = |bytes: &'a [u8]| {
+        let start = bytes
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(bytes.len());
+        &bytes[start..]
+    };
+    let rest = trim_start(&text[find_bytes(text, name)? + name.len()..]);
+    if rest.first() != Some(&b'=') {
+        return None;
+    }
+    let rest = trim_start(&rest[1..]);
+    let (quote, rest) = match rest.first() {
+        Some(&quote @ b'"') | Some(&quote @ b'\'') => (Some(quote), &rest[1..]),
+        _ => (None, rest),
+    };
+    let len = rest
+        .iter()
+        .position(|&b| match quote {
+            Some(quote) => b == quote,
+            None => b.is_ascii_whitespace() || b == b';' || b == b'"' || b == b'\'',
+        })
+        .unwrap_or(rest.len());
+    Some(&rest[..len])
+}
+
+/// The encoding of the `charset` parameter of `mime`, which may be quoted.
+fn charset_encoding(mime: &Mime) -> Option<&'static Encoding> {
+    // Only the first charset parameter counts; if its label is unknown, a
+    // later one isn't used as a fallback.
+    let value = mime.get_param(mime::CHARSET)?;
+    let mut label = value.as_ref();
+    if label.len() >= 2 && label.starts_with('"') && label.ends_with('"') {
+        label = &label[1..label.len() - 1];
+    }
+    Encoding::for_label(label.as_bytes())
+}
+
+/// A body that declares its own encoding is ASCII-compatible, so it can't be
+/// right about UTF-16.
+fn encoding_for_declared_label(label: &[u8]) -> Option<&'static Encoding> {
+    Encoding::for_label(label).map(Encoding::output_encoding)
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
 /// Returns whether `bs` is a `field-value`, as defined by
-/// [RFC 2616](http://tools.ietf.org/html/rfc2616#page-32).
+/// [RFC 2616](http://tools.ietf.org/html/rfc2616#page-32). Bytes above 0x7F
+/// are allowed as `obs-text`, as the Fetch spec requires.
 pub fn is_field_value(slice: &[u8]) -> bool {
     // Classifications of characters necessary for the [CRLF] (SP|HT) rule
     #[derive(PartialEq)]
@@ -1673,7 +3618,6 @@ pub fn is_field_value(slice: &[u8]) -> bool {
                 }
             },
             0..=31 | 127 => false, // CTLs
-            x if x > 127 => false, // non ASCII
             _ if prev == PreviousCharacter::Other || prev == PreviousCharacter::SPHT => {
                 prev = PreviousCharacter::Other;
                 true
@@ -55,6 +55,7 @@ struct FetchContext {
 pub struct FetchCanceller {
     #[ignore_malloc_size_of = "channels are hard"]
     cancel_chan: Option<ipc::IpcSender<()>>,
+    keep_alive: bool,
 }
 
 impl FetchCanceller {
@@ -70,6 +71,7 @@ impl FetchCanceller {
         self.cancel();
         let (rx, tx) = ipc::channel().unwrap();
         self.cancel_chan = Some(rx);
+        self.keep_alive = false;
         tx
     }
 
@@ -91,11 +93,19 @@ impl FetchCanceller {
     pub fn ignore(&mut self) {
         let _ = self.cancel_chan.take();
     }
+
+    /// Use this if the fetch should outlive this canceller (e.g. a keepalive
+    /// request), while still allowing it to be cancelled explicitly
+    pub fn keep_alive(&mut self) {
+        self.keep_alive = true;
+    }
 }
 
 impl Drop for FetchCanceller {
     fn drop(&mut self) {
-        self.cancel()
+        if !self.keep_alive {
+            self.cancel()
+        }
     }
 }
 
@@ -124,6 +134,7 @@ fn request_init_from_request(request: NetTraitsRequest) -> RequestBuilder {
         pipeline_id: request.pipeline_id,
         redirect_mode: request.redirect_mode,
         integrity_metadata: "".to_owned(),
+        keep_alive: request.keep_alive,
         url_list: vec![],
         parser_metadata: request.parser_metadata,
         initiator: request.initiator,
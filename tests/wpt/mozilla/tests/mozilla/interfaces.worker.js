@@ -45,6 +45,7 @@ test_interfaces([
   "PerformanceObserverEntryList",
   "PerformancePaintTiming",
   "PerformanceResourceTiming",
+  "PerformanceServerTiming",
   "ProgressEvent",
   "PromiseRejectionEvent",
   "Request",
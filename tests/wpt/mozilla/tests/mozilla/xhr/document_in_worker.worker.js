@@ -0,0 +1,31 @@
+importScripts("/resources/testharness.js");
+
+test(function() {
+  assert_false("Document" in self, "there is no Document to send from a worker");
+  assert_false("responseXML" in XMLHttpRequest.prototype);
+}, "Workers can't get hold of a Document through XMLHttpRequest");
+
+async_test(function(t) {
+  let xhr = new XMLHttpRequest();
+  xhr.open("GET", "resources/content_type_body.py?type=text/xml&content=%3Ca/%3E");
+  xhr.responseType = "document";
+  assert_equals(xhr.responseType, "", "the document response type is ignored");
+  xhr.onerror = t.unreached_func("XHR should not fail");
+  xhr.onload = t.step_func_done(function() {
+    assert_equals(xhr.response, "<a/>");
+  });
+  xhr.send();
+}, "An XML response is text in a worker");
+
+async_test(function(t) {
+  let xhr = new XMLHttpRequest();
+  xhr.open("POST", "/xhr/resources/content.py");
+  xhr.onerror = t.unreached_func("XHR should not fail");
+  xhr.onload = t.step_func_done(function() {
+    assert_equals(xhr.responseText, String(self));
+  });
+  // Anything that isn't a BodyInit is converted to a string.
+  xhr.send(self);
+}, "Sending an object that isn't a body from a worker sends its string form");
+
+done();
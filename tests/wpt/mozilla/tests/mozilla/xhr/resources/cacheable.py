@@ -0,0 +1,4 @@
+def main(request, response):
+    headers = [("Content-Type", "text/plain"),
+               ("Cache-Control", "max-age=3600")]
+    return headers, "cacheable"
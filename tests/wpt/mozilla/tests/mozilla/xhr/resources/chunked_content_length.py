@@ -0,0 +1,15 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    # A chunked body of 10 bytes, along with a conflicting Content-Length.
+    response.writer.write_status(200)
+    response.writer.write_header("Content-Type", "text/plain")
+    response.writer.write_header("Transfer-Encoding", "chunked")
+    response.writer.write_header("Content-Length", "1000")
+    response.writer.write_header("Connection", "close")
+    response.writer.end_headers()
+    response.writer.write("5\r\nhello\r\n5\r\nworld\r\n0\r\n\r\n")
+    response.close_connection = True
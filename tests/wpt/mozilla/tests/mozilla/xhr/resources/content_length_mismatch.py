@@ -0,0 +1,15 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    # Advertise a Content-Length of 10, then send `actual` bytes and close.
+    actual = int(request.GET.first("actual"))
+    response.writer.write_status(200)
+    response.writer.write_header("Content-Type", "text/plain")
+    response.writer.write_header("Content-Length", "10")
+    response.writer.write_header("Connection", "close")
+    response.writer.end_headers()
+    response.writer.write("x" * actual)
+    response.close_connection = True
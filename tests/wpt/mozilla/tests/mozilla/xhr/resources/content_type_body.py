@@ -0,0 +1,12 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    # Without a type, the response has no Content-Type at all.
+    headers = []
+    if "type" in request.GET:
+        headers.append(("Content-Type", request.GET.first("type")))
+    # "\xc3\xa9" is U+00E9 in UTF-8, but two characters in ISO-8859-1.
+    return headers, request.GET.first("content", "\xc3\xa9")
@@ -0,0 +1,19 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+import gzip
+import io
+
+
+def main(request, response):
+    # Echoes the request body, decompressed if it was sent gzip-encoded.
+    encoding = request.headers.get("Content-Encoding", "")
+    body = request.body
+    if encoding == "gzip":
+        body = gzip.GzipFile(fileobj=io.BytesIO(body)).read()
+    headers = [("Content-Type", "text/plain"),
+               ("X-Request-Content-Encoding", encoding),
+               ("X-Request-Body-Length", str(len(request.body)))]
+    return headers, body
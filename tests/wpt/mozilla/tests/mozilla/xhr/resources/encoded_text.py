@@ -0,0 +1,11 @@
+# -*- coding: utf-8 -*-
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    # Serve the same text in whichever encoding is asked for.
+    encoding = request.GET.first("encoding")
+    headers = [("Content-Type", "text/plain; charset=" + encoding)]
+    return headers, u"héllo €".encode(encoding)
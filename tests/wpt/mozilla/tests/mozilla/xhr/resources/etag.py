@@ -0,0 +1,12 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    etag = '"xhr-etag"'
+    # Keep the HTTP cache out of the way.
+    headers = [("ETag", etag), ("Cache-Control", "no-store")]
+    if request.headers.get("If-None-Match") == etag:
+        return 304, headers + [("X-Not-Modified", "1")], ""
+    return 200, headers + [("Content-Type", "text/plain")], "cached body"
@@ -0,0 +1,17 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+import time
+
+
+def main(request, response):
+    # Server-sent events, each flushed on its own after a pause.
+    count = int(request.GET.first("count", 3))
+    response.headers.set("Content-Type", "text/event-stream")
+    response.headers.set("Cache-Control", "no-cache")
+    response.write_status_headers()
+    for i in range(count):
+        response.writer.write_content("data: event %d\n\n" % i)
+        time.sleep(0.3)
@@ -0,0 +1,16 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    size = int(request.GET.first("size"))
+    chunk = "a" * 65536
+    response.headers.set("Content-Type", "text/plain")
+    response.headers.set("Content-Length", size)
+    response.write_status_headers()
+    sent = 0
+    while sent < size:
+        length = min(len(chunk), size - sent)
+        response.writer.write_content(chunk[:length])
+        sent += length
@@ -0,0 +1,24 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import time
+
+
+def main(request, response):
+    # Streams a body for five seconds, recording under `key` whether the
+    # client closed the connection before the end; `take` reports it.
+    key = request.GET.first("key")
+    if "take" in request.GET:
+        headers = [("Content-Type", "text/plain"), ("Cache-Control", "no-store")]
+        return headers, request.server.stash.take(key) or "pending"
+    response.headers.set("Content-Type", "text/plain")
+    response.headers.set("Cache-Control", "no-store")
+    response.write_status_headers()
+    for _ in range(50):
+        response.writer.write("chunk\n")
+        if not response.writer.flush():
+            request.server.stash.put(key, "cancelled")
+            return
+        time.sleep(0.1)
+    request.server.stash.put(key, "completed")
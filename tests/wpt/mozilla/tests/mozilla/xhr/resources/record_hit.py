@@ -0,0 +1,13 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    # Records that `key` was requested; `take` reports whether it was.
+    key = request.GET.first("key")
+    headers = [("Content-Type", "text/plain"), ("Cache-Control", "no-store")]
+    if "take" in request.GET:
+        return headers, "hit" if request.server.stash.take(key) else "miss"
+    request.server.stash.put(key, True)
+    return headers, "recorded"
@@ -0,0 +1,11 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+
+def main(request, response):
+    # Expired right away, so nothing lingers in the cookie jar.
+    headers = [("Content-Type", "text/plain"),
+               ("Set-Cookie", "xhr_a=1; Max-Age=0"),
+               ("Set-Cookie", "xhr_b=2; Max-Age=0; HttpOnly")]
+    return headers, "cookies"
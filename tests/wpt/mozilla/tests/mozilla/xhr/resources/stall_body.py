@@ -0,0 +1,15 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import time
+
+
+def main(request, response):
+    # Send the headers and a first chunk right away, then stall for `ms`.
+    delay = float(request.GET.first("ms")) / 1E3
+    response.headers.set("Content-Type", "text/plain")
+    response.write_status_headers()
+    response.writer.write_content("first\n")
+    time.sleep(delay)
+    response.writer.write_content("second\n")